@@ -0,0 +1,171 @@
+#![deny(missing_docs)]
+
+//! procedural macros for [dtz-identity-auth](https://crates.io/crates/dtz-identity-auth)
+use dtz_identity_auth_policy::{Part, Placeholder, Template};
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{
+    Data, DeriveInput, Fields, FnArg, ItemFn, LitStr, ReturnType, Signature, parse_macro_input,
+};
+
+#[cfg(test)]
+mod test;
+
+/// derives `dtz_identity_auth::Permission` for an enum of unit variants
+///
+/// every variant needs a `#[role("...")]` attribute with its role template,
+/// the placeholders of the templates are validated at compile time.
+/// ```ignore
+/// #[derive(Permission)]
+/// enum ContainerPermission {
+///     #[role("https://dtz.rocks/containers/admin/{context_id}")]
+///     Admin,
+/// }
+/// ```
+#[proc_macro_derive(Permission, attributes(role))]
+pub fn derive_permission(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand_permission(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+fn expand_permission(input: &DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let Data::Enum(data) = &input.data else {
+        return Err(syn::Error::new_spanned(
+            input,
+            "Permission can only be derived for enums",
+        ));
+    };
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let mut arms = Vec::new();
+    for variant in &data.variants {
+        if !matches!(variant.fields, Fields::Unit) {
+            return Err(syn::Error::new_spanned(
+                variant,
+                "Permission variants can't have fields",
+            ));
+        }
+        let mut template: Option<LitStr> = None;
+        for attr in variant.attrs.iter().filter(|a| a.path().is_ident("role")) {
+            if template.is_some() {
+                return Err(syn::Error::new_spanned(attr, "duplicate role attribute"));
+            }
+            let lit: LitStr = attr.parse_args()?;
+            validate_template(&lit.value()).map_err(|msg| syn::Error::new(lit.span(), msg))?;
+            template = Some(lit);
+        }
+        let Some(template) = template else {
+            return Err(syn::Error::new_spanned(
+                variant,
+                "missing #[role(\"...\")] attribute",
+            ));
+        };
+        let ident = &variant.ident;
+        arms.push(quote! { #name::#ident => #template });
+    }
+    Ok(quote! {
+        impl #impl_generics ::dtz_identity_auth::Permission for #name #ty_generics #where_clause {
+            fn role_template(&self) -> &'static str {
+                match self {
+                    #(#arms,)*
+                }
+            }
+        }
+    })
+}
+
+/// validates the placeholders of a role template, path and query values are not available
+/// to `DtzProfile::has`
+fn validate_template(template: &str) -> Result<(), String> {
+    let template = Template::parse(template)?;
+    for part in template.parts() {
+        if let Part::Placeholder(placeholder @ (Placeholder::Path(_) | Placeholder::Query(_))) =
+            part
+        {
+            return Err(format!(
+                "placeholder {placeholder} is not available to permissions, supported placeholders are {{identity_id}}, {{context_id}}, {{roles}} and {{claim.<name>}}"
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// guards an axum handler with a policy expression, see `dtz_identity_auth::Policy`
+///
+/// the profile is extracted before the handler runs, requests without a valid profile are
+/// rejected with `401 Unauthorized`, requests not satisfying the policy with `403 Forbidden`.
+/// invalid policy expressions fail to compile.
+/// ```ignore
+/// #[require("https://dtz.rocks/containers/admin/{context_id} OR https://dtz.rocks/admin")]
+/// async fn delete_container(Path(id): Path<String>) -> StatusCode {
+///     StatusCode::NO_CONTENT
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn require(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let expression = parse_macro_input!(attr as LitStr);
+    let function = parse_macro_input!(item as ItemFn);
+    match expand_require(&expression, function) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+fn expand_require(expression: &LitStr, function: ItemFn) -> syn::Result<proc_macro2::TokenStream> {
+    let ItemFn {
+        attrs,
+        vis,
+        sig,
+        block,
+    } = function;
+    if let Err(err) = dtz_identity_auth_policy::parse(&expression.value()) {
+        return Err(syn::Error::new(
+            expression.span(),
+            format!("invalid policy: {err}"),
+        ));
+    }
+    if sig.asyncness.is_none() {
+        return Err(syn::Error::new_spanned(
+            sig.fn_token,
+            "#[require] can only be used on async handlers",
+        ));
+    }
+    if let Some(FnArg::Receiver(receiver)) = sig.inputs.first() {
+        return Err(syn::Error::new_spanned(
+            receiver,
+            "#[require] can't be used on methods",
+        ));
+    }
+    let Signature {
+        ident,
+        generics,
+        inputs,
+        output,
+        ..
+    } = sig;
+    let (_, _, where_clause) = generics.split_for_impl();
+    let output = match output {
+        ReturnType::Default => quote! { () },
+        ReturnType::Type(_, ty) => quote! { #ty },
+    };
+    let inputs = inputs.iter();
+    Ok(quote! {
+        #(#attrs)*
+        #vis async fn #ident #generics (
+            __dtz_guard: ::dtz_identity_auth::PolicyGuard,
+            #(#inputs),*
+        ) -> ::core::result::Result<#output, ::dtz_identity_auth::__private::Response> #where_clause {
+            // the expression was validated when expanding the attribute
+            static __DTZ_POLICY: ::std::sync::LazyLock<::dtz_identity_auth::Policy> =
+                ::std::sync::LazyLock::new(|| {
+                    ::dtz_identity_auth::Policy::parse(#expression)
+                        .expect("policy validated by #[require]")
+                });
+            __dtz_guard.check(&__DTZ_POLICY)?;
+            ::core::result::Result::Ok(async move #block.await)
+        }
+    })
+}
@@ -0,0 +1,77 @@
+use super::*;
+
+#[test]
+fn valid_templates() {
+    assert!(validate_template("https://dtz.rocks/containers/admin/{context_id}").is_ok());
+    assert!(validate_template("https://dtz.rocks/identity/admin/{identity_id}").is_ok());
+    assert!(validate_template("https://dtz.rocks/admin").is_ok());
+}
+
+#[test]
+fn invalid_templates() {
+    assert_eq!(
+        validate_template("https://dtz.rocks/containers/{container_id}").unwrap_err(),
+        "unknown placeholder {container_id}"
+    );
+    assert_eq!(
+        validate_template("https://dtz.rocks/containers/{path.id}").unwrap_err(),
+        "placeholder {path.id} is not available to permissions, supported placeholders are {identity_id}, {context_id}, {roles} and {claim.<name>}"
+    );
+    assert!(validate_template("https://dtz.rocks/containers/{query.id}").is_err());
+    assert!(validate_template("https://dtz.rocks/plans/{claim.plan}/{context_id}").is_ok());
+    assert!(validate_template("https://dtz.rocks/containers/{context_id").is_err());
+    assert!(validate_template("https://dtz.rocks/containers/context_id}").is_err());
+}
+
+#[test]
+fn expand_requires_role_attribute() {
+    let input: DeriveInput = syn::parse_quote! {
+        enum Perm {
+            #[role("https://dtz.rocks/containers/admin/{context_id}")]
+            Admin,
+            Read,
+        }
+    };
+    let err = expand_permission(&input).unwrap_err();
+    assert_eq!(err.to_string(), "missing #[role(\"...\")] attribute");
+}
+
+#[test]
+fn require_on_sync_function() {
+    let expression: LitStr = syn::parse_quote!("https://dtz.rocks/admin");
+    let function: ItemFn = syn::parse_quote! {
+        fn handler() -> &'static str {
+            "ok"
+        }
+    };
+    let err = expand_require(&expression, function).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "#[require] can only be used on async handlers"
+    );
+}
+
+#[test]
+fn require_with_invalid_policy() {
+    let expression: LitStr =
+        syn::parse_quote!("https://dtz.rocks/admin OR (https://dtz.rocks/{path.id}");
+    let function: ItemFn = syn::parse_quote! {
+        async fn handler() -> &'static str {
+            "ok"
+        }
+    };
+    let err = expand_require(&expression, function).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "invalid policy: missing closing parenthesis"
+    );
+    let expression: LitStr = syn::parse_quote!("https://dtz.rocks/containers/{container}");
+    let function: ItemFn = syn::parse_quote! {
+        async fn handler() {}
+    };
+    let err = expand_require(&expression, function).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "invalid policy: unknown placeholder {container}"
+    );
+}
@@ -0,0 +1,158 @@
+use std::fmt::Display;
+
+/// attribute of the profile a policy can compare
+#[derive(Debug, Clone, PartialEq)]
+pub enum Attribute {
+    /// `identity_id`
+    IdentityId,
+    /// `context_id`
+    ContextId,
+    /// `contexts.len()`
+    ContextsLen,
+    /// `roles.len()`
+    RolesLen,
+    /// claim of the token, `claim.<name>`
+    Claim(String),
+}
+
+impl Attribute {
+    /// parses the attribute, claims need the `claim.` prefix
+    pub fn parse(name: &str) -> Result<Attribute, String> {
+        let attribute = match name {
+            "identity_id" => Attribute::IdentityId,
+            "context_id" => Attribute::ContextId,
+            "contexts.len()" => Attribute::ContextsLen,
+            "roles.len()" => Attribute::RolesLen,
+            _ => match name.strip_prefix("claim.") {
+                Some(claim) if is_valid_name(claim) => Attribute::Claim(claim.to_string()),
+                None if is_valid_name(name) => {
+                    return Err(format!(
+                        "unknown attribute `{name}`, claims are compared as `claim.{name}`"
+                    ));
+                }
+                _ => return Err(format!("unknown attribute `{name}`")),
+            },
+        };
+        Ok(attribute)
+    }
+}
+
+impl Display for Attribute {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Attribute::IdentityId => write!(f, "identity_id"),
+            Attribute::ContextId => write!(f, "context_id"),
+            Attribute::ContextsLen => write!(f, "contexts.len()"),
+            Attribute::RolesLen => write!(f, "roles.len()"),
+            Attribute::Claim(name) => write!(f, "claim.{name}"),
+        }
+    }
+}
+
+fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+/// literal value on the right hand side of a comparison
+#[derive(Debug, Clone, PartialEq)]
+pub enum Literal {
+    /// quoted or unquoted string
+    String(String),
+    /// finite number
+    Number(f64),
+    /// `true` or `false`
+    Bool(bool),
+}
+
+impl Literal {
+    /// parses an unquoted literal, words which are neither a number nor a boolean are strings
+    pub fn parse(word: &str) -> Literal {
+        match word {
+            "true" => Literal::Bool(true),
+            "false" => Literal::Bool(false),
+            _ => match word.parse::<f64>() {
+                Ok(number) if number.is_finite() => Literal::Number(number),
+                _ => Literal::String(word.to_string()),
+            },
+        }
+    }
+}
+
+impl Display for Literal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Literal::String(value) => write!(f, "{value:?}"),
+            Literal::Number(value) => write!(f, "{value}"),
+            Literal::Bool(value) => write!(f, "{value}"),
+        }
+    }
+}
+
+/// comparison operator
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Operator {
+    /// `==`
+    Eq,
+    /// `!=`
+    Ne,
+    /// `<`
+    Lt,
+    /// `<=`
+    Le,
+    /// `>`
+    Gt,
+    /// `>=`
+    Ge,
+}
+
+impl Display for Operator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let op = match self {
+            Operator::Eq => "==",
+            Operator::Ne => "!=",
+            Operator::Lt => "<",
+            Operator::Le => "<=",
+            Operator::Gt => ">",
+            Operator::Ge => ">=",
+        };
+        f.write_str(op)
+    }
+}
+
+/// comparison of a profile attribute, e.g. `claim.plan in ["pro"]` or `contexts.len() < 10`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Comparison {
+    /// comparison with an operator
+    Compare(Attribute, Operator, Literal),
+    /// the attribute equals one of the literals
+    In(Attribute, Vec<Literal>),
+}
+
+impl Comparison {
+    /// the compared attribute
+    pub fn attribute(&self) -> &Attribute {
+        match self {
+            Comparison::Compare(attribute, _, _) | Comparison::In(attribute, _) => attribute,
+        }
+    }
+}
+
+impl Display for Comparison {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Comparison::Compare(attribute, op, literal) => write!(f, "{attribute} {op} {literal}"),
+            Comparison::In(attribute, literals) => {
+                write!(f, "{attribute} in [")?;
+                for (i, literal) in literals.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{literal}")?;
+                }
+                write!(f, "]")
+            }
+        }
+    }
+}
@@ -0,0 +1,307 @@
+//! grammar of the policy expressions of [dtz-identity-auth](https://crates.io/crates/dtz-identity-auth)
+//!
+//! the grammar is shared by the runtime and the `#[require]` attribute, which rejects invalid
+//! policies at compile time. see `dtz_identity_auth::Policy` for the syntax.
+#![deny(missing_docs)]
+
+use std::fmt::Display;
+
+mod attribute;
+mod template;
+
+pub use attribute::{Attribute, Comparison, Literal, Operator};
+pub use template::{Part, Placeholder, Template};
+
+/// parsed policy expression
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    /// role template which has to be granted
+    Role(Template),
+    /// comparison of a profile attribute
+    Compare(Comparison),
+    /// negated expression
+    Not(Box<Expr>),
+    /// all expressions have to be `true`
+    And(Vec<Expr>),
+    /// one of the expressions has to be `true`
+    Or(Vec<Expr>),
+}
+
+/// parses the policy expression
+pub fn parse(expression: &str) -> Result<Expr, String> {
+    let tokens = tokenize(expression)?;
+    let mut parser = Parser { tokens, pos: 0 };
+    let expr = parser.parse_or()?;
+    if let Some(token) = parser.peek() {
+        return Err(format!("unexpected token `{token}`"));
+    }
+    Ok(expr)
+}
+
+impl Display for Expr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let join = |f: &mut std::fmt::Formatter<'_>, exprs: &[Expr], op: &str| {
+            write!(f, "(")?;
+            for (i, expr) in exprs.iter().enumerate() {
+                if i > 0 {
+                    write!(f, " {op} ")?;
+                }
+                write!(f, "{expr}")?;
+            }
+            write!(f, ")")
+        };
+        match self {
+            Expr::Role(template) => {
+                let template = template.as_str();
+                if needs_quotes(template) {
+                    write!(f, "{template:?}")
+                } else {
+                    write!(f, "{template}")
+                }
+            }
+            Expr::Compare(comparison) => write!(f, "{comparison}"),
+            Expr::Not(expr) => write!(f, "NOT {expr}"),
+            Expr::And(exprs) => join(f, exprs, "AND"),
+            Expr::Or(exprs) => join(f, exprs, "OR"),
+        }
+    }
+}
+
+/// checks whether a role template would not be read back as the same template without quotes
+fn needs_quotes(template: &str) -> bool {
+    template.contains(|c: char| c.is_whitespace() || "()&|!".contains(c))
+        || template.starts_with(['[', ']', '=', '<', '>', '"'])
+        || ["AND", "OR", "NOT", "IN"].contains(&template.to_ascii_uppercase().as_str())
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    And,
+    Or,
+    Not,
+    Open,
+    Close,
+    OpenList,
+    CloseList,
+    Comma,
+    In,
+    Operator(Operator),
+    Word(String),
+    Quoted(String),
+}
+
+impl Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::And => write!(f, "AND"),
+            Token::Or => write!(f, "OR"),
+            Token::Not => write!(f, "NOT"),
+            Token::Open => write!(f, "("),
+            Token::Close => write!(f, ")"),
+            Token::OpenList => write!(f, "["),
+            Token::CloseList => write!(f, "]"),
+            Token::Comma => write!(f, ","),
+            Token::In => write!(f, "in"),
+            Token::Operator(op) => write!(f, "{op}"),
+            Token::Word(word) => write!(f, "{word}"),
+            Token::Quoted(word) => write!(f, "{word:?}"),
+        }
+    }
+}
+
+fn tokenize(expression: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = expression.chars().peekable();
+    // within a list, words end at `,` and `]` as well
+    let mut in_list = false;
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {}
+            '(' => tokens.push(Token::Open),
+            ')' => tokens.push(Token::Close),
+            '[' => {
+                in_list = true;
+                tokens.push(Token::OpenList);
+            }
+            ']' => {
+                in_list = false;
+                tokens.push(Token::CloseList);
+            }
+            ',' if in_list => tokens.push(Token::Comma),
+            '!' if chars.peek() == Some(&'=') => {
+                chars.next();
+                tokens.push(Token::Operator(Operator::Ne));
+            }
+            '!' => tokens.push(Token::Not),
+            '=' => {
+                if chars.next() != Some('=') {
+                    return Err("expected `==`".to_string());
+                }
+                tokens.push(Token::Operator(Operator::Eq));
+            }
+            '<' | '>' => {
+                let or_equal = chars.peek() == Some(&'=');
+                if or_equal {
+                    chars.next();
+                }
+                tokens.push(Token::Operator(match (c, or_equal) {
+                    ('<', false) => Operator::Lt,
+                    ('<', true) => Operator::Le,
+                    ('>', false) => Operator::Gt,
+                    _ => Operator::Ge,
+                }));
+            }
+            '&' | '|' => {
+                if chars.next() != Some(c) {
+                    return Err(format!("expected `{c}{c}`"));
+                }
+                tokens.push(if c == '&' { Token::And } else { Token::Or });
+            }
+            '"' => {
+                let mut word = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(escaped) => word.push(escaped),
+                            None => return Err("unterminated string".to_string()),
+                        },
+                        Some(c) => word.push(c),
+                        None => return Err("unterminated string".to_string()),
+                    }
+                }
+                tokens.push(Token::Quoted(word));
+            }
+            c => {
+                let mut word = c.to_string();
+                // operators end a word, `a||b` are two roles
+                while let Some(&next) = chars.peek() {
+                    if next.is_whitespace()
+                        || "()&|!".contains(next)
+                        || (in_list && (next == ',' || next == ']'))
+                    {
+                        break;
+                    }
+                    word.push(next);
+                    chars.next();
+                }
+                // `contexts.len()` and `roles.len()` are single attributes
+                if word.ends_with(".len") {
+                    let mut ahead = chars.clone();
+                    if ahead.next() == Some('(') && ahead.next() == Some(')') {
+                        chars = ahead;
+                        word.push_str("()");
+                    }
+                }
+                let token = match word.to_ascii_uppercase().as_str() {
+                    "AND" => Token::And,
+                    "OR" => Token::Or,
+                    "NOT" => Token::Not,
+                    "IN" => Token::In,
+                    _ => Token::Word(word),
+                };
+                tokens.push(token);
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, String> {
+        let mut exprs = vec![self.parse_and()?];
+        while self.peek() == Some(&Token::Or) {
+            self.next();
+            exprs.push(self.parse_and()?);
+        }
+        Ok(if exprs.len() == 1 {
+            exprs.remove(0)
+        } else {
+            Expr::Or(exprs)
+        })
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, String> {
+        let mut exprs = vec![self.parse_unary()?];
+        while self.peek() == Some(&Token::And) {
+            self.next();
+            exprs.push(self.parse_unary()?);
+        }
+        Ok(if exprs.len() == 1 {
+            exprs.remove(0)
+        } else {
+            Expr::And(exprs)
+        })
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, String> {
+        match self.next() {
+            Some(Token::Not) => Ok(Expr::Not(Box::new(self.parse_unary()?))),
+            Some(Token::Open) => {
+                let expr = self.parse_or()?;
+                match self.next() {
+                    Some(Token::Close) => Ok(expr),
+                    _ => Err("missing closing parenthesis".to_string()),
+                }
+            }
+            Some(Token::Word(word)) => match self.peek() {
+                Some(Token::Operator(_) | Token::In) => self.parse_comparison(&word),
+                _ => Ok(Expr::Role(Template::parse(&word)?)),
+            },
+            Some(Token::Quoted(template)) => Ok(Expr::Role(Template::parse(&template)?)),
+            Some(token) => Err(format!("unexpected token `{token}`")),
+            None => Err("unexpected end of expression".to_string()),
+        }
+    }
+
+    fn parse_comparison(&mut self, attribute: &str) -> Result<Expr, String> {
+        let attribute = Attribute::parse(attribute)?;
+        let comparison = match self.next() {
+            Some(Token::Operator(op)) => Comparison::Compare(attribute, op, self.parse_literal()?),
+            _ => {
+                if self.next() != Some(Token::OpenList) {
+                    return Err("expected `[` after `in`".to_string());
+                }
+                let mut literals = Vec::new();
+                if self.peek() == Some(&Token::CloseList) {
+                    self.next();
+                } else {
+                    loop {
+                        literals.push(self.parse_literal()?);
+                        match self.next() {
+                            Some(Token::Comma) => {}
+                            Some(Token::CloseList) => break,
+                            _ => return Err("expected `,` or `]` in list".to_string()),
+                        }
+                    }
+                }
+                Comparison::In(attribute, literals)
+            }
+        };
+        Ok(Expr::Compare(comparison))
+    }
+
+    fn parse_literal(&mut self) -> Result<Literal, String> {
+        match self.next() {
+            Some(Token::Quoted(value)) => Ok(Literal::String(value)),
+            Some(Token::Word(value)) => Ok(Literal::parse(&value)),
+            Some(token) => Err(format!("expected value, found `{token}`")),
+            None => Err("expected value, found end of expression".to_string()),
+        }
+    }
+}
@@ -0,0 +1,129 @@
+use std::fmt::Display;
+
+/// parsed role template, see `dtz_identity_auth::RoleTemplate`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+    source: String,
+    parts: Vec<Part>,
+}
+
+/// literal text or placeholder of a role template
+#[derive(Debug, Clone, PartialEq)]
+pub enum Part {
+    /// text copied as is
+    Literal(String),
+    /// placeholder substituted when rendering
+    Placeholder(Placeholder),
+}
+
+/// placeholder of a role template
+#[derive(Debug, Clone, PartialEq)]
+pub enum Placeholder {
+    /// `{identity_id}`
+    IdentityId,
+    /// `{context_id}`
+    ContextId,
+    /// `{roles}`
+    Roles,
+    /// `{path.<name>}`
+    Path(String),
+    /// `{query.<name>}`
+    Query(String),
+    /// `{claim.<name>}`
+    Claim(String),
+}
+
+impl Template {
+    /// parses the template, unknown placeholders are rejected
+    pub fn parse(template: &str) -> Result<Template, String> {
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut chars = template.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some('{') | None => {
+                                return Err(format!("unclosed placeholder in `{template}`"));
+                            }
+                            Some(c) => name.push(c),
+                        }
+                    }
+                    if !literal.is_empty() {
+                        parts.push(Part::Literal(std::mem::take(&mut literal)));
+                    }
+                    parts.push(Part::Placeholder(Placeholder::parse(&name)?));
+                }
+                '}' => return Err(format!("unexpected `}}` in `{template}`")),
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            parts.push(Part::Literal(literal));
+        }
+        Ok(Template {
+            source: template.to_string(),
+            parts,
+        })
+    }
+
+    /// the template as written
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    /// literals and placeholders of the template
+    pub fn parts(&self) -> &[Part] {
+        &self.parts
+    }
+}
+
+impl Display for Template {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+impl Placeholder {
+    /// parses the name of a placeholder without braces, e.g. `path.id`
+    pub fn parse(name: &str) -> Result<Placeholder, String> {
+        let placeholder = match name {
+            "identity_id" => Placeholder::IdentityId,
+            "context_id" => Placeholder::ContextId,
+            "roles" => Placeholder::Roles,
+            _ => match name.split_once('.') {
+                Some((kind, key)) if is_valid_key(key) => match kind {
+                    "path" => Placeholder::Path(key.to_string()),
+                    "query" => Placeholder::Query(key.to_string()),
+                    "claim" => Placeholder::Claim(key.to_string()),
+                    _ => return Err(format!("unknown placeholder {{{name}}}")),
+                },
+                _ => return Err(format!("unknown placeholder {{{name}}}")),
+            },
+        };
+        Ok(placeholder)
+    }
+}
+
+impl Display for Placeholder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Placeholder::IdentityId => write!(f, "{{identity_id}}"),
+            Placeholder::ContextId => write!(f, "{{context_id}}"),
+            Placeholder::Roles => write!(f, "{{roles}}"),
+            Placeholder::Path(name) => write!(f, "{{path.{name}}}"),
+            Placeholder::Query(name) => write!(f, "{{query.{name}}}"),
+            Placeholder::Claim(name) => write!(f, "{{claim.{name}}}"),
+        }
+    }
+}
+
+fn is_valid_key(key: &str) -> bool {
+    !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
@@ -0,0 +1,385 @@
+use crate::{AuthError, AuthRejection, Decision, Policy, RequestValues, get_profile_from_request};
+use axum::{
+    extract::{OriginalUri, Request},
+    middleware::Next,
+};
+use axum_core::response::{IntoResponse, Response};
+use http::Method;
+use percent_encoding::percent_decode_str;
+use serde::Deserialize;
+use std::{
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+    time::{Duration, Instant, SystemTime},
+};
+
+/// access control list mapping route patterns and methods to policies
+///
+/// the list is read from a TOML or JSON file with one entry per rule, the first matching rule decides:
+/// ```toml
+/// unmatched = "deny"
+///
+/// [[rules]]
+/// path = "/health"
+/// public = true
+///
+/// [[rules]]
+/// path = "/containers/{container_id}"
+/// methods = ["PUT", "DELETE"]
+/// require = "https://dtz.rocks/containers/admin/{path.container_id}"
+///
+/// [[rules]]
+/// path = "/containers/{*rest}"
+/// require = "https://dtz.rocks/containers/read/{context_id}"
+/// ```
+/// path segments in braces capture the segment for `{path.<name>}` placeholders of the policy,
+/// `{*name}` captures the remaining path and `*` matches any single segment. captured values are
+/// percent-decoded like path parameters of axum. patterns match the full path of the request,
+/// including the prefix of nested routers. rules without methods apply to all methods.
+/// requests without matching rule are denied unless `unmatched = "allow"` is set.
+#[derive(Debug, Clone)]
+pub struct Acl {
+    rules: Vec<AclRule>,
+    allow_unmatched: bool,
+}
+
+#[derive(Debug, Clone)]
+struct AclRule {
+    pattern: Vec<Segment>,
+    methods: Vec<Method>,
+    access: Access,
+}
+
+#[derive(Debug, Clone)]
+enum Access {
+    Public,
+    Require(Policy),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Literal(String),
+    Param(String),
+    Any,
+    Rest(String),
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AclDefinition {
+    #[serde(default)]
+    unmatched: Unmatched,
+    #[serde(default)]
+    rules: Vec<RuleDefinition>,
+}
+
+#[derive(Deserialize, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum Unmatched {
+    #[default]
+    Deny,
+    Allow,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleDefinition {
+    path: String,
+    #[serde(default)]
+    methods: Vec<String>,
+    require: Option<String>,
+    #[serde(default)]
+    public: bool,
+}
+
+/// decision of the [`Acl`] for a request
+#[derive(Debug, Clone)]
+pub(crate) enum AclMatch<'a> {
+    Public,
+    Require(&'a Policy, RequestValues),
+    Unmatched,
+}
+
+impl Acl {
+    /// parses the list from TOML
+    pub fn parse_toml(content: &str) -> Result<Acl, String> {
+        let definition: AclDefinition =
+            toml::from_str(content).map_err(|e| format!("invalid acl: {e}"))?;
+        Acl::from_definition(definition)
+    }
+
+    /// parses the list from JSON
+    pub fn parse_json(content: &str) -> Result<Acl, String> {
+        let definition: AclDefinition =
+            serde_json::from_str(content).map_err(|e| format!("invalid acl: {e}"))?;
+        Acl::from_definition(definition)
+    }
+
+    /// reads the list from a file, files ending with `.json` are parsed as JSON, all others as TOML
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Acl, String> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("failed to read {}: {e}", path.display()))?;
+        if path.extension().is_some_and(|ext| ext == "json") {
+            Acl::parse_json(&content)
+        } else {
+            Acl::parse_toml(&content)
+        }
+    }
+
+    fn from_definition(definition: AclDefinition) -> Result<Acl, String> {
+        let mut rules = Vec::new();
+        for rule in definition.rules {
+            let access = match (rule.public, rule.require) {
+                (true, None) => Access::Public,
+                (false, Some(require)) => Access::Require(
+                    Policy::parse(&require).map_err(|e| format!("rule {}: {e}", rule.path))?,
+                ),
+                (true, Some(_)) => {
+                    return Err(format!("rule {}: public rules can't require", rule.path));
+                }
+                (false, None) => {
+                    return Err(format!("rule {}: either public or require", rule.path));
+                }
+            };
+            let mut methods = Vec::new();
+            for method in rule.methods {
+                if method == "*" {
+                    methods.clear();
+                    break;
+                }
+                let method = Method::from_bytes(method.to_ascii_uppercase().as_bytes())
+                    .map_err(|_| format!("rule {}: invalid method {method}", rule.path))?;
+                methods.push(method);
+            }
+            rules.push(AclRule {
+                pattern: parse_pattern(&rule.path)?,
+                methods,
+                access,
+            });
+        }
+        Ok(Acl {
+            rules,
+            allow_unmatched: definition.unmatched == Unmatched::Allow,
+        })
+    }
+
+    /// finds the first rule matching the request
+    pub(crate) fn matching(&self, method: &Method, path: &str) -> AclMatch<'_> {
+        for rule in &self.rules {
+            if !rule.methods.is_empty() && !rule.methods.contains(method) {
+                continue;
+            }
+            let Some(values) = match_pattern(&rule.pattern, path) else {
+                continue;
+            };
+            return match &rule.access {
+                Access::Public => AclMatch::Public,
+                Access::Require(policy) => AclMatch::Require(policy, values),
+            };
+        }
+        if self.allow_unmatched {
+            AclMatch::Public
+        } else {
+            AclMatch::Unmatched
+        }
+    }
+}
+
+fn parse_pattern(pattern: &str) -> Result<Vec<Segment>, String> {
+    if !pattern.starts_with('/') {
+        return Err(format!("rule {pattern}: path must start with `/`"));
+    }
+    let segments: Vec<&str> = pattern.split('/').filter(|s| !s.is_empty()).collect();
+    let mut result = Vec::new();
+    for (i, segment) in segments.iter().enumerate() {
+        let parsed = match segment.strip_prefix('{').and_then(|s| s.strip_suffix('}')) {
+            Some(name) if name.starts_with('*') => {
+                if i + 1 != segments.len() {
+                    return Err(format!(
+                        "rule {pattern}: `{{{name}}}` must be the last segment"
+                    ));
+                }
+                Segment::Rest(name[1..].to_string())
+            }
+            Some(name) if !name.is_empty() => Segment::Param(name.to_string()),
+            Some(_) => return Err(format!("rule {pattern}: empty parameter name")),
+            None if *segment == "*" => Segment::Any,
+            None => Segment::Literal(segment.to_string()),
+        };
+        result.push(parsed);
+    }
+    Ok(result)
+}
+
+fn match_pattern(pattern: &[Segment], path: &str) -> Option<RequestValues> {
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    let mut values = RequestValues::new();
+    for (i, expected) in pattern.iter().enumerate() {
+        match expected {
+            Segment::Rest(name) => {
+                // like axum, the rest matches at least one segment
+                if segments.len() <= i {
+                    return None;
+                }
+                if !name.is_empty() {
+                    values = values.with_path(name, decode(&segments.get(i..)?.join("/"))?);
+                }
+                return Some(values);
+            }
+            Segment::Literal(literal) => {
+                if segments.get(i) != Some(&literal.as_str()) {
+                    return None;
+                }
+            }
+            Segment::Param(name) => values = values.with_path(name, decode(segments.get(i)?)?),
+            Segment::Any => {
+                segments.get(i)?;
+            }
+        }
+    }
+    if segments.len() == pattern.len() {
+        Some(values)
+    } else {
+        None
+    }
+}
+
+/// percent-decodes a captured value, `None` if it is not valid utf-8
+fn decode(value: &str) -> Option<String> {
+    percent_decode_str(value)
+        .decode_utf8()
+        .ok()
+        .map(|value| value.into_owned())
+}
+
+/// policy reported in the decision for requests without matching rule
+const UNMATCHED_POLICY: &str = "no matching acl rule";
+
+/// interval in which the file is checked for changes
+const RELOAD_INTERVAL: Duration = Duration::from_secs(1);
+
+/// [`Acl`] read from a file and reloaded when the file changes
+///
+/// the file is checked for changes at most once per second while requests are served,
+/// a file which fails to parse keeps the previous list in place
+#[derive(Debug, Clone)]
+pub struct AclFile {
+    path: PathBuf,
+    state: Arc<RwLock<AclState>>,
+}
+
+#[derive(Debug)]
+struct AclState {
+    acl: Arc<Acl>,
+    modified: Option<SystemTime>,
+    checked: Instant,
+}
+
+impl AclFile {
+    /// reads the file, fails if the initial list can't be read
+    pub fn open(path: impl Into<PathBuf>) -> Result<AclFile, String> {
+        let path = path.into();
+        let modified = modified(&path);
+        let acl = Acl::from_file(&path)?;
+        Ok(AclFile {
+            path,
+            state: Arc::new(RwLock::new(AclState {
+                acl: Arc::new(acl),
+                modified,
+                checked: Instant::now(),
+            })),
+        })
+    }
+
+    /// current list, reloaded if the file changed
+    pub fn acl(&self) -> Arc<Acl> {
+        let state = self.state.read().unwrap();
+        if state.checked.elapsed() < RELOAD_INTERVAL {
+            return state.acl.clone();
+        }
+        drop(state);
+        let mut state = self.state.write().unwrap();
+        state.checked = Instant::now();
+        let modified = modified(&self.path);
+        if modified != state.modified {
+            if let Ok(acl) = Acl::from_file(&self.path) {
+                state.acl = Arc::new(acl);
+                state.modified = modified;
+            }
+        }
+        state.acl.clone()
+    }
+
+    /// reloads the file immediately, the previous list is kept on error
+    pub fn reload(&self) -> Result<(), String> {
+        let modified = modified(&self.path);
+        let acl = Acl::from_file(&self.path)?;
+        let mut state = self.state.write().unwrap();
+        state.acl = Arc::new(acl);
+        state.modified = modified;
+        state.checked = Instant::now();
+        Ok(())
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// middleware enforcing the [`AclFile`], to be used with `axum::middleware::from_fn_with_state`
+/// ```ignore
+/// let acl = AclFile::open("acl.toml")?;
+/// let app = Router::new()
+///     .route("/containers/{container_id}", put(handler))
+///     .layer(middleware::from_fn_with_state(acl, enforce_acl));
+/// ```
+/// public routes are passed through, all others are authenticated like [`DtzProfile`](crate::DtzProfile)
+/// and checked against the policy of the matching rule. requests without matching rule are
+/// authenticated as well and then rejected with `403 Forbidden`.
+pub async fn enforce_acl(
+    axum::extract::State(acl): axum::extract::State<AclFile>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let acl = acl.acl();
+    let (mut parts, body) = req.into_parts();
+    let path = match parts.extensions.get::<OriginalUri>() {
+        Some(OriginalUri(uri)) => uri.path().to_string(),
+        None => parts.uri.path().to_string(),
+    };
+    let matching = acl.matching(&parts.method, &path);
+    if let AclMatch::Public = matching {
+        return next.run(Request::from_parts(parts, body)).await;
+    }
+    let profile = match get_profile_from_request(&mut parts).await {
+        Ok(profile) => profile,
+        Err(err) => return AuthRejection::new(err, &parts).into_response(),
+    };
+    let (policy, values) = match matching {
+        AclMatch::Require(policy, values) => (policy, values),
+        _ => {
+            let decision = Decision {
+                allowed: false,
+                policy: UNMATCHED_POLICY.to_string(),
+                required: Vec::new(),
+                held: profile.effective_roles(),
+                closest: Vec::new(),
+                failed_clauses: vec![format!("{} {path}", parts.method)],
+            };
+            return AuthRejection::new(AuthError::Forbidden(Box::new(decision)), &parts)
+                .into_response();
+        }
+    };
+    let query = parts.uri.query().unwrap_or_default();
+    let pairs: Vec<(String, String)> = serde_urlencoded::from_str(query).unwrap_or_default();
+    let values = pairs.into_iter().fold(values, |values, (name, value)| {
+        values.with_query(name, value)
+    });
+    if !policy.evaluate_with(&profile, &values) {
+        let decision = policy.explain_with(&profile, &values);
+        return AuthRejection::new(AuthError::Forbidden(Box::new(decision)), &parts)
+            .into_response();
+    }
+    next.run(Request::from_parts(parts, body)).await
+}
@@ -0,0 +1,61 @@
+use crate::DtzProfile;
+use dtz_identity_auth_policy::{Attribute, Comparison, Literal, Operator};
+use serde_json::Value;
+
+/// value of the attribute, `None` if the claim is missing
+pub(crate) fn resolve(attribute: &Attribute, profile: &DtzProfile) -> Option<Value> {
+    match attribute {
+        Attribute::IdentityId => Some(Value::String(profile.identity_id.to_string())),
+        Attribute::ContextId => Some(Value::String(profile.context_id.to_string())),
+        Attribute::ContextsLen => Some(Value::from(profile.contexts.len())),
+        Attribute::RolesLen => Some(Value::from(profile.roles.len())),
+        Attribute::Claim(name) => profile.claims().get(name).cloned(),
+    }
+}
+
+/// evaluates the comparison, comparisons of missing attributes are always `false`
+pub(crate) fn evaluate(comparison: &Comparison, profile: &DtzProfile) -> bool {
+    let Some(value) = resolve(comparison.attribute(), profile) else {
+        return false;
+    };
+    match comparison {
+        Comparison::Compare(_, op, literal) => compare(&value, *op, literal),
+        Comparison::In(_, literals) => literals.iter().any(|l| matches(l, &value)),
+    }
+}
+
+fn matches(literal: &Literal, value: &Value) -> bool {
+    match (literal, value) {
+        (Literal::String(literal), Value::String(value)) => literal == value,
+        (Literal::Number(literal), Value::Number(value)) => value.as_f64() == Some(*literal),
+        (Literal::Bool(literal), Value::Bool(value)) => literal == value,
+        _ => false,
+    }
+}
+
+fn compare(value: &Value, op: Operator, literal: &Literal) -> bool {
+    match op {
+        Operator::Eq => matches(literal, value),
+        Operator::Ne => !matches(literal, value),
+        _ => {
+            let ordering = match (value, literal) {
+                (Value::Number(value), Literal::Number(literal)) => {
+                    value.as_f64().and_then(|v| v.partial_cmp(literal))
+                }
+                (Value::String(value), Literal::String(literal)) => {
+                    Some(value.as_str().cmp(literal))
+                }
+                _ => None,
+            };
+            let Some(ordering) = ordering else {
+                return false;
+            };
+            match op {
+                Operator::Lt => ordering.is_lt(),
+                Operator::Le => ordering.is_le(),
+                Operator::Gt => ordering.is_gt(),
+                _ => ordering.is_ge(),
+            }
+        }
+    }
+}
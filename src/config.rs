@@ -0,0 +1,318 @@
+use crate::{AuthError, AuthMethod, LoginRedirect, session::AUTH_COOKIE};
+use axum_core::response::Response;
+use http::request::Parts;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt::Display,
+    sync::{Arc, RwLock},
+};
+
+static GLOBAL_CONFIG: Lazy<RwLock<AuthConfig>> = Lazy::new(|| RwLock::new(AuthConfig::default()));
+
+/// mechanism to authenticate a request, without the credential itself
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum AuthMechanism {
+    /// `dtz-auth` cookie
+    Cookie,
+    /// `Authorization: Bearer` header
+    Bearer,
+    /// `Authorization: Basic` header
+    Basic,
+    /// `x-api-key` header
+    ApiKeyHeader,
+    /// `apiKey` query parameter
+    ApiKeyQuery,
+}
+
+impl AuthMechanism {
+    /// all supported mechanisms
+    pub const ALL: [AuthMechanism; 5] = [
+        AuthMechanism::Cookie,
+        AuthMechanism::Bearer,
+        AuthMechanism::Basic,
+        AuthMechanism::ApiKeyHeader,
+        AuthMechanism::ApiKeyQuery,
+    ];
+}
+
+impl Display for AuthMechanism {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            AuthMechanism::Cookie => "cookie",
+            AuthMechanism::Bearer => "bearer",
+            AuthMechanism::Basic => "basic",
+            AuthMechanism::ApiKeyHeader => "x-api-key header",
+            AuthMechanism::ApiKeyQuery => "apiKey query parameter",
+        };
+        f.write_str(name)
+    }
+}
+
+impl AuthMethod {
+    /// mechanism of this method, `None` if the method is unknown
+    pub fn mechanism(&self) -> Option<AuthMechanism> {
+        match self {
+            AuthMethod::Unknown => None,
+            AuthMethod::Cookie => Some(AuthMechanism::Cookie),
+            AuthMethod::Bearer => Some(AuthMechanism::Bearer),
+            AuthMethod::Basic { .. } => Some(AuthMechanism::Basic),
+            AuthMethod::ApiKeyHeader { .. } => Some(AuthMechanism::ApiKeyHeader),
+            AuthMethod::ApiKeyQuery { .. } => Some(AuthMechanism::ApiKeyQuery),
+        }
+    }
+}
+
+/// configuration of the request authentication
+///
+/// the configuration can be set globally with [`set_auth_config`] or per route/router
+/// by adding it as request extension, e.g. with `axum::Extension`:
+/// ```ignore
+/// let config = AuthConfig::new().deny(AuthMechanism::ApiKeyQuery);
+/// let app = Router::new()
+///     .route("/", post(handler))
+///     .layer(Extension(config));
+/// ```
+#[derive(Debug, Clone)]
+pub struct AuthConfig {
+    allowed: Vec<AuthMechanism>,
+    precedence: Vec<AuthMechanism>,
+    reject_conflicting: bool,
+    debug_decisions: bool,
+    realm: String,
+    problem_details: bool,
+    problem_types: Option<String>,
+    login_redirect: Option<LoginRedirect>,
+    rejection_handler: Option<RejectionHandler>,
+    cookie_name: String,
+    legacy_cookie_names: Vec<String>,
+}
+
+/// function building the response of a rejection
+type RejectionFn = dyn Fn(&AuthError, &Parts) -> Option<Response> + Send + Sync;
+
+#[derive(Clone)]
+struct RejectionHandler(Arc<RejectionFn>);
+
+impl std::fmt::Debug for RejectionHandler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("RejectionHandler")
+    }
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        Self {
+            allowed: AuthMechanism::ALL.to_vec(),
+            precedence: AuthMechanism::ALL.to_vec(),
+            reject_conflicting: false,
+            debug_decisions: false,
+            realm: "dtz".to_string(),
+            problem_details: false,
+            problem_types: None,
+            login_redirect: None,
+            rejection_handler: None,
+            cookie_name: AUTH_COOKIE.to_string(),
+            legacy_cookie_names: Vec::new(),
+        }
+    }
+}
+
+impl AuthConfig {
+    /// creates the default configuration, which allows all mechanisms
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// only allow the given mechanisms
+    pub fn allow(mut self, mechanisms: impl IntoIterator<Item = AuthMechanism>) -> Self {
+        self.allowed = Vec::new();
+        for mechanism in mechanisms {
+            if !self.allowed.contains(&mechanism) {
+                self.allowed.push(mechanism);
+            }
+        }
+        self
+    }
+
+    /// disallow the given mechanism
+    pub fn deny(mut self, mechanism: AuthMechanism) -> Self {
+        self.allowed.retain(|m| *m != mechanism);
+        self
+    }
+
+    /// checks whether the mechanism is allowed
+    pub fn is_allowed(&self, mechanism: AuthMechanism) -> bool {
+        self.allowed.contains(&mechanism)
+    }
+
+    /// allowed mechanisms
+    pub fn allowed(&self) -> &[AuthMechanism] {
+        &self.allowed
+    }
+
+    /// order in which the mechanisms are tried, a failed or absent mechanism falls through to the next one
+    ///
+    /// mechanisms missing in the given order are tried last, in their default order
+    pub fn precedence(mut self, order: impl IntoIterator<Item = AuthMechanism>) -> Self {
+        let mut precedence: Vec<AuthMechanism> = Vec::new();
+        for mechanism in order.into_iter().chain(AuthMechanism::ALL) {
+            if !precedence.contains(&mechanism) {
+                precedence.push(mechanism);
+            }
+        }
+        self.precedence = precedence;
+        self
+    }
+
+    /// order in which the mechanisms are tried
+    pub fn precedence_order(&self) -> &[AuthMechanism] {
+        &self.precedence
+    }
+
+    /// reject requests which present credentials for more than one mechanism
+    pub fn reject_conflicting(mut self, reject: bool) -> Self {
+        self.reject_conflicting = reject;
+        self
+    }
+
+    /// checks whether requests with more than one credential are rejected
+    pub fn rejects_conflicting(&self) -> bool {
+        self.reject_conflicting
+    }
+
+    /// explain denied authorization decisions in the body of `403 Forbidden` responses
+    ///
+    /// the explanation lists roles of the identity, so this should only be enabled for debugging
+    pub fn debug_decisions(mut self, debug: bool) -> Self {
+        self.debug_decisions = debug;
+        self
+    }
+
+    /// checks whether denied decisions are explained in the response
+    pub fn debugs_decisions(&self) -> bool {
+        self.debug_decisions
+    }
+
+    /// realm of the `WWW-Authenticate` challenges, `dtz` by default
+    pub fn realm(mut self, realm: impl Into<String>) -> Self {
+        self.realm = realm.into();
+        self
+    }
+
+    /// realm of the `WWW-Authenticate` challenges
+    pub fn realm_name(&self) -> &str {
+        &self.realm
+    }
+
+    /// render rejections as RFC 9457 problem details (`application/problem+json`) instead of plain text
+    pub fn problem_details(mut self, enabled: bool) -> Self {
+        self.problem_details = enabled;
+        self
+    }
+
+    /// checks whether rejections are rendered as problem details
+    pub fn uses_problem_details(&self) -> bool {
+        self.problem_details
+    }
+
+    /// base uri of the problem types, the type of a rejection becomes `{base}/{code}`,
+    /// e.g. `https://dtz.rocks/problems/invalid_credentials`. without base the type is `about:blank`
+    pub fn problem_types(mut self, base: impl Into<String>) -> Self {
+        self.problem_types = Some(base.into());
+        self
+    }
+
+    /// base uri of the problem types
+    pub fn problem_type_base(&self) -> Option<&str> {
+        self.problem_types.as_deref()
+    }
+
+    /// redirect unauthenticated browser requests to the login page instead of `401 Unauthorized`
+    pub fn login_redirect(mut self, redirect: LoginRedirect) -> Self {
+        self.login_redirect = Some(redirect);
+        self
+    }
+
+    /// redirect of unauthenticated browser requests, if enabled
+    pub fn redirects_to_login(&self) -> Option<&LoginRedirect> {
+        self.login_redirect.as_ref()
+    }
+
+    /// builds the responses of rejections with the function instead of the built-in formats
+    ///
+    /// the function receives the reason of the rejection and the request, returning `None`
+    /// falls back to the built-in response, e.g. for handlers which only log
+    /// ```
+    /// use axum::response::IntoResponse;
+    /// use dtz_identity_auth::{AuthConfig, AuthError};
+    /// let config = AuthConfig::new().rejection_handler(|error, _req| match error {
+    ///     AuthError::MissingCredentials => Some((error.status(), "please log in").into_response()),
+    ///     _ => None,
+    /// });
+    /// ```
+    pub fn rejection_handler(
+        mut self,
+        handler: impl Fn(&AuthError, &Parts) -> Option<Response> + Send + Sync + 'static,
+    ) -> Self {
+        self.rejection_handler = Some(RejectionHandler(Arc::new(handler)));
+        self
+    }
+
+    /// runs the rejection handler, `None` if there is no handler or it fell back
+    pub(crate) fn handle_rejection(&self, error: &AuthError, req: &Parts) -> Option<Response> {
+        self.rejection_handler
+            .as_ref()
+            .and_then(|handler| (handler.0)(error, req))
+    }
+
+    /// name of the cookie carrying the token, `dtz-auth` by default
+    ///
+    /// prefixed names like `__Host-dtz-auth` are supported, see [`AuthCookie::name`](crate::AuthCookie::name)
+    pub fn cookie_name(mut self, name: impl Into<String>) -> Self {
+        self.cookie_name = name.into();
+        self
+    }
+
+    /// name of the cookie carrying the token
+    pub fn auth_cookie_name(&self) -> &str {
+        &self.cookie_name
+    }
+
+    /// accepts the token from a cookie with a previous name as well, the current name takes precedence
+    ///
+    /// browsers can be moved to the current name with the [`migrate_legacy_cookies`](crate::migrate_legacy_cookies) middleware
+    pub fn legacy_cookie_name(mut self, name: impl Into<String>) -> Self {
+        let name = name.into();
+        if !self.legacy_cookie_names.contains(&name) {
+            self.legacy_cookie_names.push(name);
+        }
+        self
+    }
+
+    /// previous names of the cookie
+    pub fn legacy_cookie_names(&self) -> &[String] {
+        &self.legacy_cookie_names
+    }
+
+    /// all accepted cookie names, in order of preference
+    pub(crate) fn cookie_names(&self) -> Vec<&str> {
+        std::iter::once(self.cookie_name.as_str())
+            .chain(self.legacy_cookie_names.iter().map(String::as_str))
+            .collect()
+    }
+
+    /// configuration for the request, a request extension takes precedence over the global configuration
+    pub(crate) fn for_request(req: &Parts) -> AuthConfig {
+        match req.extensions.get::<AuthConfig>() {
+            Some(config) => config.clone(),
+            None => GLOBAL_CONFIG.read().unwrap().clone(),
+        }
+    }
+}
+
+/// sets the global configuration, used for all requests without a configuration extension
+pub fn set_auth_config(config: AuthConfig) {
+    *GLOBAL_CONFIG.write().unwrap() = config;
+}
@@ -0,0 +1,173 @@
+use crate::{AuthError, AuthRejection, DtzProfile};
+use axum::extract::RawPathParams;
+use axum_core::extract::FromRequestParts;
+use dtz_identifier::ContextId;
+use http::request::Parts;
+use serde_json::Value;
+use std::{collections::HashMap, ops::Deref};
+use uuid::Uuid;
+
+/// name of the path parameter holding the context id
+const CONTEXT_PATH_PARAM: &str = "context_id";
+
+/// claim mapping context ids to their roles
+const CONTEXT_ROLES_CLAIM: &str = "context_roles";
+
+impl DtzProfile {
+    /// roles grouped by the context they apply to
+    ///
+    /// the grouping is taken from the `context_roles` claim if the token has one,
+    /// otherwise roles are grouped by their resource if it is a context id
+    /// (e.g. `https://dtz.rocks/containers/admin/{context_id}`).
+    /// roles which don't apply to a context are not part of the map.
+    pub fn roles_by_context(&self) -> HashMap<ContextId, Vec<String>> {
+        let mut result: HashMap<ContextId, Vec<String>> = HashMap::new();
+        if let Some(Value::Object(claim)) = self.claims().get(CONTEXT_ROLES_CLAIM) {
+            for (context, roles) in claim {
+                let Some(context_id) = parse_context_id(context) else {
+                    continue;
+                };
+                let roles = roles
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(Value::as_str)
+                    .map(String::from);
+                result.entry(context_id).or_default().extend(roles);
+            }
+            return result;
+        }
+        for role in &self.roles {
+            if let Some(context_id) = context_of_role(role) {
+                result.entry(context_id).or_default().push(role.clone());
+            }
+        }
+        result
+    }
+
+    /// roles which apply to the given context
+    pub fn roles_in_context(&self, context_id: &ContextId) -> Vec<String> {
+        self.roles_by_context()
+            .remove(context_id)
+            .unwrap_or_default()
+    }
+
+    /// the profile switched to another member context, `None` if the identity is not a member
+    ///
+    /// roles scoped to a context are replaced by the roles of the context, see
+    /// [`DtzProfile::roles_by_context`]. roles which don't apply to a context, e.g. a global
+    /// `https://dtz.rocks/admin`, are kept.
+    pub fn in_context(&self, context_id: &ContextId) -> Option<DtzProfile> {
+        if !self.is_member(context_id) {
+            return None;
+        }
+        let mut by_context = self.roles_by_context();
+        let scoped: Vec<&String> = by_context.values().flatten().collect();
+        let mut roles: Vec<String> = self
+            .roles
+            .iter()
+            .filter(|role| !scoped.contains(role) && context_of_role(role).is_none())
+            .cloned()
+            .collect();
+        roles.extend(by_context.remove(context_id).unwrap_or_default());
+        let mut profile = self.clone();
+        profile.roles = roles;
+        profile.context_id = context_id.clone();
+        Some(profile)
+    }
+
+    /// checks the roles of another member context for the required scope, see [`DtzProfile::require`]
+    pub fn require_in(&self, context_id: &ContextId, required_role: &str) -> bool {
+        match self.in_context(context_id) {
+            Some(profile) => profile.require(required_role),
+            None => false,
+        }
+    }
+}
+
+/// context of a role whose resource is a context id
+fn context_of_role(role: &str) -> Option<ContextId> {
+    let parsed = crate::Role::parse(role).ok()?;
+    parsed.resource().as_deref().and_then(parse_context_id)
+}
+
+/// parses a context id, with or without the `context-` prefix
+fn parse_context_id(value: &str) -> Option<ContextId> {
+    match ContextId::try_from(value) {
+        Ok(id) => Some(id),
+        Err(_) => Uuid::parse_str(value)
+            .ok()
+            .map(|id| ContextId { id: id.to_string() }),
+    }
+}
+
+/// profile scoped to the context of the matched path, e.g. `/context/{context_id}/...`
+///
+/// the extraction guarantees that the identity is a member of the context of the path,
+/// the `context_id` of the profile is switched to that context. cross-tenant access is
+/// rejected with `403 Forbidden`.
+#[derive(Debug, Clone)]
+pub struct ContextScopedProfile {
+    profile: DtzProfile,
+}
+
+impl ContextScopedProfile {
+    /// context of the path
+    pub fn context_id(&self) -> &ContextId {
+        &self.profile.context_id
+    }
+
+    /// profile within the context of the path
+    pub fn profile(&self) -> &DtzProfile {
+        &self.profile
+    }
+
+    /// profile within the context of the path
+    pub fn into_profile(self) -> DtzProfile {
+        self.profile
+    }
+}
+
+impl Deref for ContextScopedProfile {
+    type Target = DtzProfile;
+
+    fn deref(&self) -> &Self::Target {
+        &self.profile
+    }
+}
+
+impl<S> FromRequestParts<S> for ContextScopedProfile
+where
+    S: Send + Sync,
+{
+    type Rejection = AuthRejection;
+
+    async fn from_request_parts(req: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let profile = DtzProfile::from_request_parts(req, state).await?;
+        let value = match RawPathParams::from_request_parts(req, state).await {
+            Ok(params) => params
+                .iter()
+                .find(|(name, _)| *name == CONTEXT_PATH_PARAM)
+                .map(|(_, value)| value.to_string()),
+            Err(_) => None,
+        };
+        scope_to_context(profile, value.as_deref()).map_err(|err| AuthRejection::new(err, req))
+    }
+}
+
+/// scopes the profile to the context id of the path
+pub(crate) fn scope_to_context(
+    mut profile: DtzProfile,
+    value: Option<&str>,
+) -> Result<ContextScopedProfile, AuthError> {
+    let Some(value) = value else {
+        return Err(AuthError::MissingContextParameter);
+    };
+    let context_id =
+        ContextId::try_from(value).map_err(|_| AuthError::InvalidContext(value.to_string()))?;
+    if !profile.is_member(&context_id) {
+        return Err(AuthError::NotAMember(context_id.to_string()));
+    }
+    profile.context_id = context_id;
+    Ok(ContextScopedProfile { profile })
+}
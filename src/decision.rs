@@ -0,0 +1,230 @@
+use crate::{
+    DtzProfile, Policy, RequestValues, Role, attribute, policy, replace_placeholder, role, template,
+};
+use axum_core::response::{IntoResponse, Response};
+use dtz_identity_auth_policy::{Expr, Template};
+use http::{StatusCode, header};
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+
+/// explanation of an authorization decision
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Decision {
+    /// whether access is granted
+    pub allowed: bool,
+    /// policy or role template that was evaluated
+    pub policy: String,
+    /// required roles after placeholder substitution
+    pub required: Vec<String>,
+    /// roles held by the profile, including implied roles
+    pub held: Vec<String>,
+    /// held roles closest to the required roles which are not granted
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub closest: Vec<String>,
+    /// clauses of the policy which failed
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub failed_clauses: Vec<String>,
+}
+
+impl Decision {
+    /// `403 Forbidden` response for a denied decision, the explanation is only part of the body in debug mode
+    pub fn rejection(&self, debug: bool) -> Response {
+        if debug {
+            let body = serde_json::to_string(self).unwrap_or_default();
+            (
+                StatusCode::FORBIDDEN,
+                [(header::CONTENT_TYPE, "application/json")],
+                body,
+            )
+                .into_response()
+        } else {
+            (StatusCode::FORBIDDEN, "forbidden").into_response()
+        }
+    }
+}
+
+impl Display for Decision {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.allowed {
+            return write!(f, "allowed by `{}`", self.policy);
+        }
+        write!(f, "denied by `{}`", self.policy)?;
+        if !self.failed_clauses.is_empty() {
+            write!(f, ", failed: {}", self.failed_clauses.join(", "))?;
+        }
+        if !self.closest.is_empty() {
+            write!(f, ", closest held roles: {}", self.closest.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+impl Policy {
+    /// evaluates the policy and explains the decision
+    pub fn explain(&self, profile: &DtzProfile) -> Decision {
+        self.explain_with(profile, &RequestValues::default())
+    }
+
+    /// evaluates the policy with the values of the request and explains the decision
+    pub fn explain_with(&self, profile: &DtzProfile, values: &RequestValues) -> Decision {
+        let held = profile.effective_roles();
+        let mut required = Vec::new();
+        collect_required(self.expr(), profile, values, &mut required);
+        let allowed = self.evaluate_with(profile, values);
+        let mut failed_clauses = Vec::new();
+        if !allowed {
+            collect_failed(self.expr(), profile, values, &mut failed_clauses);
+        }
+        let missing: Vec<&String> = required
+            .iter()
+            .filter(|r| !role::holds_role(&profile.roles, r))
+            .collect();
+        Decision {
+            allowed,
+            policy: self.to_string(),
+            closest: if allowed {
+                Vec::new()
+            } else {
+                closest_roles(&held, &missing)
+            },
+            required,
+            held,
+            failed_clauses,
+        }
+    }
+}
+
+impl DtzProfile {
+    /// checks the profile for the required scope like [`DtzProfile::require`] and explains the decision
+    pub fn explain(&self, required_role: &str) -> Decision {
+        let (scope, allowed) = match replace_placeholder(required_role, self) {
+            Ok(scope) => {
+                let allowed = role::holds_role(&self.roles, &scope);
+                (scope, allowed)
+            }
+            Err(err) => (format!("{required_role} ({err})"), false),
+        };
+        let held = self.effective_roles();
+        Decision {
+            allowed,
+            policy: required_role.to_string(),
+            closest: if allowed {
+                Vec::new()
+            } else {
+                closest_roles(&held, &[&scope])
+            },
+            failed_clauses: if allowed {
+                Vec::new()
+            } else {
+                vec![scope.clone()]
+            },
+            required: vec![scope],
+            held,
+        }
+    }
+}
+
+fn render(role: &Template, profile: &DtzProfile, values: &RequestValues) -> String {
+    template::render(role, profile, values).unwrap_or_else(|err| format!("{role} ({err})"))
+}
+
+fn collect_required(
+    expr: &Expr,
+    profile: &DtzProfile,
+    values: &RequestValues,
+    required: &mut Vec<String>,
+) {
+    match expr {
+        Expr::Role(template) => {
+            let role = render(template, profile, values);
+            if !required.contains(&role) {
+                required.push(role);
+            }
+        }
+        Expr::Compare(_) => {}
+        Expr::Not(expr) => collect_required(expr, profile, values, required),
+        Expr::And(exprs) | Expr::Or(exprs) => {
+            for expr in exprs {
+                collect_required(expr, profile, values, required);
+            }
+        }
+    }
+}
+
+/// collects the clauses responsible for the expression evaluating to `false`
+fn collect_failed(
+    expr: &Expr,
+    profile: &DtzProfile,
+    values: &RequestValues,
+    failed: &mut Vec<String>,
+) {
+    match expr {
+        Expr::Role(template) => failed.push(render(template, profile, values)),
+        Expr::Compare(comparison) => match attribute::resolve(comparison.attribute(), profile) {
+            Some(value) => failed.push(format!("{comparison} (actual {value})")),
+            None => failed.push(format!("{comparison} (missing)")),
+        },
+        Expr::Not(inner) => failed.push(format!("NOT {}", render_expr(inner, profile, values))),
+        Expr::And(exprs) => {
+            for expr in exprs
+                .iter()
+                .filter(|e| !policy::evaluate(e, profile, values))
+            {
+                collect_failed(expr, profile, values, failed);
+            }
+        }
+        Expr::Or(exprs) => {
+            for expr in exprs {
+                collect_failed(expr, profile, values, failed);
+            }
+        }
+    }
+}
+
+fn render_expr(expr: &Expr, profile: &DtzProfile, values: &RequestValues) -> String {
+    match expr {
+        Expr::Role(template) => render(template, profile, values),
+        other => other.to_string(),
+    }
+}
+
+/// held roles sharing the longest common prefix with one of the missing roles
+fn closest_roles(held: &[String], missing: &[&String]) -> Vec<String> {
+    let mut closest = Vec::new();
+    for missing in missing {
+        let Ok(missing) = Role::parse(missing) else {
+            continue;
+        };
+        let mut best = 0;
+        let mut candidates: Vec<&String> = Vec::new();
+        for role in held {
+            let Ok(parsed) = Role::parse(role) else {
+                continue;
+            };
+            if parsed.origin() != missing.origin() {
+                continue;
+            }
+            let common = parsed
+                .segments()
+                .iter()
+                .zip(missing.segments())
+                .take_while(|(a, b)| a == b)
+                .count();
+            if common == 0 || common < best {
+                continue;
+            }
+            if common > best {
+                best = common;
+                candidates.clear();
+            }
+            candidates.push(role);
+        }
+        for candidate in candidates {
+            if !closest.contains(candidate) {
+                closest.push(candidate.clone());
+            }
+        }
+    }
+    closest
+}
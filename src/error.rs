@@ -0,0 +1,268 @@
+use crate::{AuthConfig, AuthMechanism, Decision, StepUp};
+use axum_core::response::{IntoResponse, Response};
+use http::{HeaderValue, StatusCode, header, request::Parts};
+use std::fmt::Display;
+
+/// reason the authentication or authorization of a request failed
+#[derive(Debug, Clone, PartialEq)]
+pub enum AuthError {
+    /// the request presented no credentials
+    MissingCredentials,
+    /// the credentials of the mechanism were rejected
+    InvalidCredentials {
+        /// mechanism of the rejected credentials
+        mechanism: AuthMechanism,
+        /// reason of the rejection
+        message: String,
+    },
+    /// the request only presented credentials for mechanisms which are not allowed
+    MechanismNotAllowed {
+        /// presented mechanisms
+        presented: Vec<AuthMechanism>,
+        /// allowed mechanisms
+        allowed: Vec<AuthMechanism>,
+    },
+    /// the request presented credentials for more than one mechanism
+    ConflictingCredentials(Vec<AuthMechanism>),
+    /// the identity is not a member of the requested context
+    NotAMember(String),
+    /// the context id of the path is invalid
+    InvalidContext(String),
+    /// the route has no `{context_id}` path parameter
+    MissingContextParameter,
+    /// the authentication does not meet the step-up requirement
+    InsufficientUserAuthentication {
+        /// requirement of the route
+        step_up: StepUp,
+        /// the part of the requirement which is not met
+        description: String,
+    },
+    /// the profile does not satisfy the required roles or policy
+    Forbidden(Box<Decision>),
+}
+
+impl AuthError {
+    /// status code of the response
+    pub fn status(&self) -> StatusCode {
+        match self {
+            AuthError::MissingCredentials
+            | AuthError::InvalidCredentials { .. }
+            | AuthError::MechanismNotAllowed { .. }
+            | AuthError::ConflictingCredentials(_)
+            | AuthError::InsufficientUserAuthentication { .. } => StatusCode::UNAUTHORIZED,
+            AuthError::NotAMember(_) | AuthError::Forbidden(_) => StatusCode::FORBIDDEN,
+            AuthError::InvalidContext(_) => StatusCode::BAD_REQUEST,
+            AuthError::MissingContextParameter => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// machine readable code of the error, e.g. `invalid_credentials`
+    pub fn code(&self) -> &'static str {
+        match self {
+            AuthError::MissingCredentials => "missing_credentials",
+            AuthError::InvalidCredentials { .. } => "invalid_credentials",
+            AuthError::MechanismNotAllowed { .. } => "mechanism_not_allowed",
+            AuthError::ConflictingCredentials(_) => "conflicting_credentials",
+            AuthError::NotAMember(_) => "not_a_member",
+            AuthError::InvalidContext(_) => "invalid_context",
+            AuthError::MissingContextParameter => "missing_context_parameter",
+            AuthError::InsufficientUserAuthentication { .. } => "insufficient_user_authentication",
+            AuthError::Forbidden(_) => "forbidden",
+        }
+    }
+
+    /// `WWW-Authenticate` challenges of the error (RFC 6750), the `Basic` challenge is only
+    /// added for missing or invalid credentials if basic authentication is allowed
+    fn challenges(&self, config: &AuthConfig) -> Vec<String> {
+        let realm = quote(config.realm_name());
+        let bearer = |error: &str, description: String| {
+            format!(
+                "Bearer realm=\"{realm}\", error=\"{error}\", error_description=\"{}\"",
+                quote(&description)
+            )
+        };
+        let mut challenges = match self {
+            AuthError::MissingCredentials => vec![format!("Bearer realm=\"{realm}\"")],
+            AuthError::InvalidCredentials { .. } => vec![bearer("invalid_token", self.to_string())],
+            AuthError::MechanismNotAllowed { .. } | AuthError::ConflictingCredentials(_) => {
+                vec![bearer("invalid_request", self.to_string())]
+            }
+            AuthError::InsufficientUserAuthentication {
+                step_up,
+                description,
+            } => vec![step_up.challenge_header(&realm, description)],
+            AuthError::NotAMember(_) => vec![bearer("insufficient_scope", self.to_string())],
+            AuthError::Forbidden(decision) => vec![format!(
+                "{}, scope=\"{}\"",
+                bearer("insufficient_scope", "forbidden".to_string()),
+                quote(&decision.required.join(" "))
+            )],
+            AuthError::InvalidContext(_) | AuthError::MissingContextParameter => Vec::new(),
+        };
+        let offers_basic = matches!(
+            self,
+            AuthError::MissingCredentials | AuthError::InvalidCredentials { .. }
+        );
+        if offers_basic && config.is_allowed(AuthMechanism::Basic) {
+            challenges.push(format!("Basic realm=\"{realm}\""));
+        }
+        challenges
+    }
+}
+
+impl Display for AuthError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AuthError::MissingCredentials => write!(f, "no authorization header"),
+            AuthError::InvalidCredentials { message, .. } => write!(f, "{message}"),
+            AuthError::MechanismNotAllowed { presented, allowed } => write!(
+                f,
+                "authentication method not allowed ({}), accepted methods: {}",
+                join_mechanisms(presented),
+                join_mechanisms(allowed)
+            ),
+            AuthError::ConflictingCredentials(presented) => write!(
+                f,
+                "conflicting credentials presented ({})",
+                join_mechanisms(presented)
+            ),
+            AuthError::NotAMember(context_id) => write!(f, "not a member of context {context_id}"),
+            AuthError::InvalidContext(context_id) => write!(f, "invalid context id {context_id}"),
+            AuthError::MissingContextParameter => {
+                write!(f, "route has no {{context_id}} path parameter")
+            }
+            AuthError::InsufficientUserAuthentication { description, .. } => {
+                write!(f, "{description}")
+            }
+            AuthError::Forbidden(decision) => write!(f, "{decision}"),
+        }
+    }
+}
+
+impl std::error::Error for AuthError {}
+
+fn join_mechanisms(mechanisms: &[AuthMechanism]) -> String {
+    if mechanisms.is_empty() {
+        return "none".to_string();
+    }
+    mechanisms
+        .iter()
+        .map(|m| m.to_string())
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+/// removes characters which can't be part of a quoted header value
+pub(crate) fn quote(value: &str) -> String {
+    value.replace(['"', '\\', '\r', '\n'], "")
+}
+
+/// rejection of the extractors, renders the [`AuthError`] with the configuration of the request
+#[derive(Debug)]
+pub struct AuthRejection {
+    error: AuthError,
+    config: AuthConfig,
+    request_id: Option<String>,
+    login_location: Option<String>,
+    handled: Option<Response>,
+}
+
+impl AuthRejection {
+    /// rejection of the request with the error, runs the rejection handler of the configuration
+    pub fn new(error: AuthError, req: &Parts) -> Self {
+        let config = AuthConfig::for_request(req);
+        let login_location = match config.redirects_to_login() {
+            Some(redirect) if error.status() == StatusCode::UNAUTHORIZED => redirect.location(req),
+            _ => None,
+        };
+        let handled = config.handle_rejection(&error, req);
+        AuthRejection {
+            error,
+            config,
+            login_location,
+            handled,
+            request_id: req
+                .headers
+                .get(REQUEST_ID_HEADER)
+                .and_then(|id| id.to_str().ok())
+                .map(String::from),
+        }
+    }
+
+    /// reason of the rejection
+    pub fn error(&self) -> &AuthError {
+        &self.error
+    }
+
+    /// reason of the rejection
+    pub fn into_error(self) -> AuthError {
+        self.error
+    }
+
+    /// RFC 9457 problem details of the rejection
+    fn problem_details(&self) -> Response {
+        let status = self.error.status();
+        let debug = self.config.debugs_decisions();
+        let mut problem = serde_json::Map::new();
+        let problem_type = match self.config.problem_type_base() {
+            Some(base) => format!("{}/{}", base.trim_end_matches('/'), self.error.code()),
+            None => "about:blank".to_string(),
+        };
+        problem.insert("type".to_string(), problem_type.into());
+        problem.insert(
+            "title".to_string(),
+            status.canonical_reason().unwrap_or_default().into(),
+        );
+        problem.insert("status".to_string(), status.as_u16().into());
+        let detail = match &self.error {
+            AuthError::Forbidden(_) if !debug => "forbidden".to_string(),
+            error => error.to_string(),
+        };
+        problem.insert("detail".to_string(), detail.into());
+        problem.insert("code".to_string(), self.error.code().into());
+        if let Some(request_id) = &self.request_id {
+            problem.insert("requestId".to_string(), request_id.clone().into());
+        }
+        if let AuthError::Forbidden(decision) = &self.error
+            && debug
+        {
+            problem.insert(
+                "decision".to_string(),
+                serde_json::to_value(decision).unwrap_or_default(),
+            );
+        }
+        (
+            status,
+            [(header::CONTENT_TYPE, "application/problem+json")],
+            serde_json::Value::Object(problem).to_string(),
+        )
+            .into_response()
+    }
+}
+
+/// header carrying the id of the request, added to problem details
+const REQUEST_ID_HEADER: &str = "x-request-id";
+
+impl IntoResponse for AuthRejection {
+    fn into_response(self) -> Response {
+        if let Some(response) = self.handled {
+            return response;
+        }
+        if let Some(location) = &self.login_location {
+            return (StatusCode::FOUND, [(header::LOCATION, location.as_str())]).into_response();
+        }
+        let mut response = match &self.error {
+            _ if self.config.uses_problem_details() => self.problem_details(),
+            AuthError::Forbidden(decision) => decision.rejection(self.config.debugs_decisions()),
+            error => (error.status(), error.to_string()).into_response(),
+        };
+        for challenge in self.error.challenges(&self.config) {
+            if let Ok(value) = HeaderValue::from_str(&challenge) {
+                response
+                    .headers_mut()
+                    .append(header::WWW_AUTHENTICATE, value);
+            }
+        }
+        response
+    }
+}
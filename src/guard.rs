@@ -0,0 +1,77 @@
+use crate::{AuthError, AuthRejection, DtzProfile, Policy, RequestValues};
+use axum_core::{
+    extract::FromRequestParts,
+    response::{IntoResponse, Response},
+};
+use http::request::Parts;
+use std::ops::Deref;
+
+/// authenticated profile together with the request values needed to check policies,
+/// used by the `#[require]` attribute
+#[derive(Debug, Clone)]
+pub struct PolicyGuard {
+    profile: DtzProfile,
+    values: RequestValues,
+    parts: Parts,
+}
+
+impl PolicyGuard {
+    #[cfg(test)]
+    pub(crate) fn new(profile: DtzProfile, values: RequestValues, parts: Parts) -> Self {
+        PolicyGuard {
+            profile,
+            values,
+            parts,
+        }
+    }
+
+    /// checks the policy, a denied decision is turned into a `403 Forbidden` response
+    ///
+    /// the body explains the decision if [`AuthConfig::debug_decisions`] is enabled
+    #[allow(clippy::result_large_err)]
+    pub fn check(&self, policy: &Policy) -> Result<(), Response> {
+        if policy.evaluate_with(&self.profile, &self.values) {
+            return Ok(());
+        }
+        let decision = policy.explain_with(&self.profile, &self.values);
+        Err(
+            AuthRejection::new(AuthError::Forbidden(Box::new(decision)), &self.parts)
+                .into_response(),
+        )
+    }
+
+    /// the authenticated profile
+    pub fn profile(&self) -> &DtzProfile {
+        &self.profile
+    }
+
+    /// path and query values of the request
+    pub fn values(&self) -> &RequestValues {
+        &self.values
+    }
+}
+
+impl Deref for PolicyGuard {
+    type Target = DtzProfile;
+
+    fn deref(&self) -> &Self::Target {
+        &self.profile
+    }
+}
+
+impl<S> FromRequestParts<S> for PolicyGuard
+where
+    S: Send + Sync,
+{
+    type Rejection = AuthRejection;
+
+    async fn from_request_parts(req: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let profile = DtzProfile::from_request_parts(req, state).await?;
+        let Ok(values) = RequestValues::from_request_parts(req, state).await;
+        Ok(PolicyGuard {
+            profile,
+            values,
+            parts: req.clone(),
+        })
+    }
+}
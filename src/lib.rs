@@ -5,7 +5,7 @@ use axum_core::extract::{FromRequestParts, OptionalFromRequestParts};
 use base64::{Engine as _, engine::general_purpose};
 use cookie::Cookie;
 use dtz_identifier::{ApiKeyId, ContextId, IdentityId};
-use http::{StatusCode, header, header::HeaderValue, request::Parts};
+use http::{header, header::HeaderValue, request::Parts};
 use http_body_util::BodyExt;
 use hyper::{Method, Request};
 use hyper_util::{client::legacy::Client, rt::TokioExecutor};
@@ -14,12 +14,54 @@ use lru_time_cache::LruCache;
 use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::{sync::Mutex, time::Duration};
+use std::{
+    sync::Mutex,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 use uuid::Uuid;
 
+extern crate self as dtz_identity_auth;
+
+mod acl;
+mod attribute;
+mod config;
+mod context;
+mod decision;
+mod error;
+mod guard;
+mod permission;
+mod policy;
+mod redirect;
+mod role;
+mod session;
+mod step_up;
+mod template;
 #[cfg(test)]
 mod test;
 
+pub use acl::{Acl, AclFile, enforce_acl};
+pub use config::{AuthConfig, AuthMechanism, set_auth_config};
+pub use context::ContextScopedProfile;
+pub use decision::Decision;
+pub use dtz_identity_auth_macros::{Permission, require};
+pub use error::{AuthError, AuthRejection};
+pub use guard::PolicyGuard;
+#[cfg(any(test, feature = "test-util"))]
+pub use permission::FakePermissionChecker;
+pub use permission::{IdentityPermissionChecker, PermissionChecker, PermissionFuture};
+
+#[doc(hidden)]
+pub mod __private {
+    pub use axum_core::response::Response;
+}
+pub use cookie::SameSite;
+pub use policy::Policy;
+pub use redirect::LoginRedirect;
+pub use role::{Role, RoleGraph, set_role_graph};
+pub use session::{AuthCookie, SetCookies, migrate_legacy_cookies};
+pub use step_up::{StepUp, StepUpProfile, StepUpRequirement};
+pub use template::{RequestValues, RoleTemplate};
+
 /// public key used for JWT signature verification
 const PUBLIC_KEY: &str = r#"-----BEGIN PUBLIC KEY-----
 MIIBIjANBgkqhkiG9w0BAQEFAAOCAQ8AMIIBCgKCAQEA0gVBfnAa7748XyjOYXQ5
@@ -46,6 +88,97 @@ pub struct DtzProfile {
     pub contexts: Vec<ContextId>,
     /// raw token of the request, if api key was used, a new token is generated
     pub token: String,
+    /// time the token was issued, in seconds since the unix epoch (`iat` claim)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub issued_at: Option<u64>,
+    /// time the token expires, in seconds since the unix epoch (`exp` claim)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<u64>,
+    /// unique identifier of the token (`jti` claim)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_id: Option<String>,
+    /// issuer of the token (`iss` claim)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub issuer: Option<String>,
+    /// intended audience of the token (`aud` claim)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub audience: Option<Vec<String>>,
+    /// mechanism that was used to authenticate the request
+    #[serde(default)]
+    pub auth_method: AuthMethod,
+    /// time the identity authenticated, in seconds since the unix epoch (`auth_time` claim)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth_time: Option<u64>,
+    /// authentication context class (`acr` claim)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub acr: Option<String>,
+    /// authentication methods, e.g. `pwd` or `mfa` (`amr` claim)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub amr: Vec<String>,
+}
+
+/// permission of a service with its role template, usually derived with [`macro@Permission`]
+///
+/// ```
+/// use dtz_identity_auth::{DtzProfile, Permission};
+///
+/// #[derive(Permission)]
+/// enum ContainerPermission {
+///     #[role("https://dtz.rocks/containers/admin/{context_id}")]
+///     Admin,
+/// }
+///
+/// fn handler(profile: DtzProfile) -> bool {
+///     profile.has(ContainerPermission::Admin)
+/// }
+/// ```
+pub trait Permission {
+    /// role template of the permission, see [`DtzProfile::require`]
+    fn role_template(&self) -> &'static str;
+}
+
+/// mechanism that was used to authenticate a request
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(rename_all = "camelCase", tag = "type")]
+pub enum AuthMethod {
+    /// the mechanism is not known, e.g. the profile was created manually
+    #[default]
+    Unknown,
+    /// `dtz-auth` cookie
+    Cookie,
+    /// `Authorization: Bearer` header
+    Bearer,
+    /// `Authorization: Basic` header, the api key is set if `apikey` was used as user
+    Basic {
+        /// api key used as password
+        #[serde(rename = "apiKey", skip_serializing_if = "Option::is_none")]
+        api_key: Option<ApiKeyId>,
+    },
+    /// `x-api-key` header
+    ApiKeyHeader {
+        /// api key of the header
+        #[serde(rename = "apiKey")]
+        api_key: ApiKeyId,
+    },
+    /// `apiKey` query parameter
+    ApiKeyQuery {
+        /// api key of the query parameter
+        #[serde(rename = "apiKey")]
+        api_key: ApiKeyId,
+    },
+}
+
+impl AuthMethod {
+    /// api key used for authentication, if any
+    pub fn api_key(&self) -> Option<&ApiKeyId> {
+        match self {
+            AuthMethod::Basic { api_key } => api_key.as_ref(),
+            AuthMethod::ApiKeyHeader { api_key } | AuthMethod::ApiKeyQuery { api_key } => {
+                Some(api_key)
+            }
+            _ => None,
+        }
+    }
 }
 
 impl DtzProfile {
@@ -54,10 +187,53 @@ impl DtzProfile {
     /// - {identity_id}
     /// - {context_id}
     /// - {roles}
+    ///
+    /// granted roles imply the required role as described in [`Role`],
+    /// unknown placeholders are never granted, see [`DtzProfile::try_require`]
     #[allow(dead_code)]
     pub fn require(&self, required_role: &str) -> bool {
-        let scope = replace_placeholder(required_role, self);
-        self.roles.contains(&scope)
+        self.try_require(required_role).unwrap_or(false)
+    }
+
+    /// checks the profile for the required scope like [`DtzProfile::require`],
+    /// fails if the template has unknown placeholders or placeholders without value
+    pub fn try_require(&self, required_role: &str) -> Result<bool, String> {
+        let scope = replace_placeholder(required_role, self)?;
+        Ok(role::holds_role(&self.roles, &scope))
+    }
+
+    /// checks the profile for a typed [`Permission`]
+    pub fn has<P: Permission>(&self, permission: P) -> bool {
+        self.require(permission.role_template())
+    }
+
+    /// checks whether the context is the current context or one of the available contexts
+    pub fn is_member(&self, context_id: &ContextId) -> bool {
+        self.context_id == *context_id || self.contexts.contains(context_id)
+    }
+
+    /// all claims of the token, empty if the token can't be decoded
+    ///
+    /// the token was verified when the profile was created, the claims are not verified again
+    pub fn claims(&self) -> serde_json::Map<String, Value> {
+        token_claims(&self.token)
+    }
+
+    /// granted roles including all roles implied by the installed [`RoleGraph`]
+    pub fn effective_roles(&self) -> Vec<String> {
+        role::expand_roles(&self.roles)
+    }
+
+    /// remaining lifetime of the token, `None` if the token carries no expiry
+    ///
+    /// an already expired token returns a zero duration
+    pub fn expires_in(&self) -> Option<Duration> {
+        let expires_at = self.expires_at?;
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        Some(Duration::from_secs(expires_at.saturating_sub(now)))
     }
 }
 
@@ -65,14 +241,12 @@ impl<B> FromRequestParts<B> for DtzProfile
 where
     B: Send + std::marker::Sync,
 {
-    type Rejection = (StatusCode, String);
+    type Rejection = AuthRejection;
 
     async fn from_request_parts(req: &mut Parts, _state: &B) -> Result<Self, Self::Rejection> {
-        let result = get_profile_from_request(req).await;
-        match result {
-            Ok(profile) => Ok(profile),
-            Err(e) => Err((StatusCode::UNAUTHORIZED, e)),
-        }
+        get_profile_from_request(req)
+            .await
+            .map_err(|err| AuthRejection::new(err, req))
     }
 }
 
@@ -80,7 +254,7 @@ impl<B> OptionalFromRequestParts<B> for DtzProfile
 where
     B: Send + std::marker::Sync,
 {
-    type Rejection = (StatusCode, String);
+    type Rejection = AuthRejection;
 
     async fn from_request_parts(
         req: &mut Parts,
@@ -89,136 +263,224 @@ where
         let result = get_profile_from_request(req).await;
         match result {
             Ok(profile) => Ok(Some(profile)),
+            // authenticated, but not allowed to use the requested context
+            Err(err @ AuthError::NotAMember(_)) => Err(AuthRejection::new(err, req)),
             Err(_e) => Ok(None),
         }
     }
 }
 
-async fn get_profile_from_request(req: &mut Parts) -> Result<DtzProfile, String> {
-    let headers = req.headers.clone();
-    let cookie_headers = headers.get_all(header::COOKIE);
-    let authorization: Option<&HeaderValue> = headers.get(header::AUTHORIZATION);
-    let header_api_key: Option<&HeaderValue> = headers.get("x-api-key");
-    let header_context_id: Option<&HeaderValue> = headers.get("x-dtz-context");
-    let profile: DtzProfile;
-    if cookie_headers.iter().next().is_some() {
-        let mut found = None;
-        for cookie in cookie_headers.iter() {
-            if let Ok(p) = verify_token_from_cookie(cookie.clone()) {
-                found = Some(p);
-                break;
+async fn get_profile_from_request(req: &mut Parts) -> Result<DtzProfile, AuthError> {
+    let config = AuthConfig::for_request(req);
+    let presented = presented_mechanisms(req, &config);
+    if presented.is_empty() {
+        return Err(AuthError::MissingCredentials);
+    }
+    if config.rejects_conflicting() && presented.len() > 1 {
+        return Err(AuthError::ConflictingCredentials(presented));
+    }
+    let mut first_error = None;
+    for mechanism in presented.iter().filter(|m| config.is_allowed(**m)) {
+        match authenticate(*mechanism, req, &config).await {
+            Ok(profile) => {
+                // api keys select their context when requesting the token
+                if profile.auth_method.api_key().is_some() {
+                    return Ok(profile);
+                }
+                return select_context(profile, req);
             }
-        }
-        match found {
-            Some(p) => {
-                profile = p;
+            Err(message) => {
+                first_error.get_or_insert(AuthError::InvalidCredentials {
+                    mechanism: *mechanism,
+                    message,
+                });
             }
-            None => {
-                return Err("no valid token found in cookie".to_string());
+        }
+    }
+    match first_error {
+        Some(err) => Err(err),
+        None => Err(AuthError::MechanismNotAllowed {
+            presented,
+            allowed: config.allowed().to_vec(),
+        }),
+    }
+}
+
+/// switches the profile to the context requested by `x-dtz-context` or the `contextId` query parameter
+///
+/// the requested context has to be the current context of the token or one of its `contexts`
+fn select_context(mut profile: DtzProfile, req: &Parts) -> Result<DtzProfile, AuthError> {
+    let requested = match req.headers.get("x-dtz-context") {
+        Some(context_id) if !context_id.is_empty() => {
+            Some(context_id.to_str().unwrap_or_default().to_string())
+        }
+        _ => get_auth_params(req).context_id.filter(|c| !c.is_empty()),
+    };
+    let Some(requested) = requested else {
+        return Ok(profile);
+    };
+    let context_id = ContextId::try_from(requested.as_str())
+        .map_err(|_| AuthError::NotAMember(requested.clone()))?;
+    if !profile.is_member(&context_id) {
+        return Err(AuthError::NotAMember(requested));
+    }
+    profile.context_id = context_id;
+    Ok(profile)
+}
+
+/// mechanisms presented by the request, in the given order of precedence
+fn presented_mechanisms(req: &Parts, config: &AuthConfig) -> Vec<AuthMechanism> {
+    let cookie_names = config.cookie_names();
+    config
+        .precedence_order()
+        .iter()
+        .copied()
+        .filter(|mechanism| match mechanism {
+            AuthMechanism::Cookie => {
+                cookie_header(req).is_some_and(|cookie| has_auth_cookie(&cookie, &cookie_names))
             }
+            AuthMechanism::Basic => req
+                .headers
+                .get(header::AUTHORIZATION)
+                .is_some_and(|value| value.as_bytes().starts_with(b"Basic ")),
+            AuthMechanism::Bearer => req
+                .headers
+                .get(header::AUTHORIZATION)
+                .is_some_and(|value| !value.as_bytes().starts_with(b"Basic ")),
+            AuthMechanism::ApiKeyHeader => req.headers.contains_key("x-api-key"),
+            AuthMechanism::ApiKeyQuery => get_auth_params(req).api_key.is_some(),
+        })
+        .collect()
+}
+
+/// all `Cookie` headers joined into one, chunks of a token may be split across them (RFC 9113 8.2.3)
+fn cookie_header(req: &Parts) -> Option<HeaderValue> {
+    let cookies: Vec<&str> = req
+        .headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .collect();
+    if cookies.is_empty() {
+        return None;
+    }
+    HeaderValue::from_str(&cookies.join("; ")).ok()
+}
+
+fn has_auth_cookie(cookie: &HeaderValue, names: &[&str]) -> bool {
+    let cookie_str = cookie.to_str().unwrap_or_default();
+    let cookies: Vec<Cookie> = Cookie::split_parse(cookie_str).flatten().collect();
+    names
+        .iter()
+        .any(|name| session::cookie_value(&cookies, name).is_some())
+}
+
+fn get_auth_params(req: &Parts) -> GetAuthParams {
+    let query = req.uri.query().unwrap_or_default();
+    serde_urlencoded::from_str(query).unwrap_or(GetAuthParams {
+        api_key: None,
+        context_id: None,
+    })
+}
+
+async fn authenticate(
+    mechanism: AuthMechanism,
+    req: &Parts,
+    config: &AuthConfig,
+) -> Result<DtzProfile, String> {
+    let headers = &req.headers;
+    match mechanism {
+        AuthMechanism::Cookie => {
+            let mut profile = cookie_header(req)
+                .and_then(|cookie| verify_token_from_cookie(cookie, &config.cookie_names()).ok())
+                .ok_or("no valid token found in cookie")?;
+            profile.auth_method = AuthMethod::Cookie;
+            Ok(profile)
         }
-    } else if let Some(authorization) = authorization {
-        let auth_str = authorization.to_str().unwrap();
-        if auth_str.starts_with("Basic ") {
-            match verify_basic_auth(authorization).await {
-                Ok(p) => {
-                    profile = p;
-                }
-                Err(_) => {
-                    return Err("not authorized".to_string());
-                }
+        AuthMechanism::Basic => {
+            let authorization = headers.get(header::AUTHORIZATION).unwrap();
+            verify_basic_auth(authorization)
+                .await
+                .map_err(|_| "not authorized".to_string())
+        }
+        AuthMechanism::Bearer => {
+            let authorization = headers.get(header::AUTHORIZATION).unwrap();
+            if !authorization.as_bytes().starts_with(b"Bearer ") {
+                return Err("not authorized".to_string());
             }
-        } else if auth_str.starts_with("Bearer ") {
             match verify_token_from_bearer(authorization.clone()) {
-                Ok(p) => {
-                    profile = p;
-                }
-                Err(_) => {
-                    return Err("not authorized".to_string());
+                Ok(mut profile) => {
+                    profile.auth_method = AuthMethod::Bearer;
+                    Ok(profile)
                 }
+                Err(_) => Err("not authorized".to_string()),
             }
-        } else {
-            return Err("not authorized".to_string());
         }
-    } else if let Some(header_api_key) = header_api_key {
-        if let Some(context_id) = header_context_id {
-            if context_id.is_empty() {
-                let result = ApiKeyId::try_from(header_api_key.to_str().unwrap());
-                return match result {
-                    Ok(key) => verify_api_key(&key, None).await,
-                    Err(err) => Err(err),
-                };
-            } else {
-                let api_key = ApiKeyId::try_from(header_api_key.to_str().unwrap());
-                let context_id = ContextId::try_from(context_id.to_str().unwrap());
-                match (api_key, context_id) {
-                    (Ok(api_key), Ok(context_id)) => {
-                        return verify_api_key(&api_key, Some(&context_id)).await;
-                    }
-                    _ => {
-                        //fail
-                        return Err("not authorized".to_string());
+        AuthMechanism::ApiKeyHeader => {
+            let header_api_key = headers.get("x-api-key").unwrap();
+            let api_key = ApiKeyId::try_from(header_api_key.to_str().unwrap())?;
+            let result = match headers.get("x-dtz-context") {
+                Some(context_id) if !context_id.is_empty() => {
+                    match ContextId::try_from(context_id.to_str().unwrap()) {
+                        Ok(context_id) => verify_api_key(&api_key, Some(&context_id)).await,
+                        Err(_) => Err("not authorized".to_string()),
                     }
                 }
-            }
-        } else {
-            let result = ApiKeyId::try_from(header_api_key.to_str().unwrap());
-            return match result {
-                Ok(key) => verify_api_key(&key, None).await,
-                Err(err) => Err(err),
+                _ => verify_api_key(&api_key, None).await,
             };
+            let mut profile = result?;
+            profile.auth_method = AuthMethod::ApiKeyHeader { api_key };
+            Ok(profile)
         }
-    } else {
-        //look for GET params
-        let query = req.uri.query().unwrap_or_default();
-        let value: GetAuthParams = serde_urlencoded::from_str(query).unwrap();
-        return verify_query_params(value).await;
+        AuthMechanism::ApiKeyQuery => verify_query_params(get_auth_params(req)).await,
     }
-    Ok(profile)
 }
 
 async fn verify_query_params(value: GetAuthParams) -> Result<DtzProfile, String> {
-    if value.api_key.is_some() {
-        if value.context_id.is_none() {
-            let result = ApiKeyId::try_from(value.api_key.unwrap_or_default().as_str());
-            match result {
-                Ok(key) => verify_api_key(&key, None).await,
-                Err(err) => Err(err),
-            }
-        } else {
-            let api_key = ApiKeyId::try_from(value.api_key.unwrap_or_default().as_str());
-            let context_id = ContextId::try_from(value.context_id.unwrap_or_default().as_str());
-            match (api_key, context_id) {
-                (Ok(api_key), Ok(context_id)) => verify_api_key(&api_key, Some(&context_id)).await,
-                _ => {
+    if let Some(api_key) = value.api_key {
+        let api_key = ApiKeyId::try_from(api_key.as_str())?;
+        let mut profile = match value.context_id {
+            None => verify_api_key(&api_key, None).await?,
+            Some(context_id) => match ContextId::try_from(context_id.as_str()) {
+                Ok(context_id) => verify_api_key(&api_key, Some(&context_id)).await?,
+                Err(_) => {
                     //fail
-                    Err("not authorized".to_string())
+                    return Err("not authorized".to_string());
                 }
-            }
-        }
+            },
+        };
+        profile.auth_method = AuthMethod::ApiKeyQuery { api_key };
+        Ok(profile)
     } else {
         Err("no authorization header".to_string())
     }
 }
 
-fn verify_token_from_cookie(cookie: HeaderValue) -> Result<DtzProfile, String> {
+/// decodes the claims of the token without verifying it, empty if the token can't be decoded
+pub(crate) fn token_claims(token: &str) -> serde_json::Map<String, Value> {
+    let Some(payload) = token.split('.').nth(1) else {
+        return serde_json::Map::new();
+    };
+    let decoded = general_purpose::URL_SAFE_NO_PAD
+        .decode(payload)
+        .or_else(|_| general_purpose::STANDARD_NO_PAD.decode(payload))
+        .unwrap_or_default();
+    match serde_json::from_slice(&decoded) {
+        Ok(Value::Object(claims)) => claims,
+        _ => serde_json::Map::new(),
+    }
+}
+
+/// verifies the token of the first cookie name present in the header, names are in order of preference
+fn verify_token_from_cookie(cookie: HeaderValue, names: &[&str]) -> Result<DtzProfile, String> {
     let cookie_str = cookie.to_str().unwrap();
-    let mut final_cookie: Option<String> = None;
-    for cookie in Cookie::split_parse(cookie_str) {
-        match cookie {
-            Ok(cookie) => {
-                if cookie.name() == "dtz-auth" {
-                    final_cookie = Some(cookie.value().to_string());
-                }
-            }
-            Err(_) => continue,
+    let cookies: Vec<Cookie> = Cookie::split_parse(cookie_str).flatten().collect();
+    for name in names {
+        if let Some(token) = session::cookie_value(&cookies, name) {
+            return crate::verify_token(token);
         }
     }
-    if let Some(token) = final_cookie {
-        crate::verify_token(token.to_string())
-    } else {
-        Err("no valid token found in cookie".to_string())
-    }
+    Err("no valid token found in cookie".to_string())
 }
 
 async fn verify_basic_auth(bearer: &HeaderValue) -> Result<DtzProfile, String> {
@@ -231,15 +493,16 @@ async fn verify_basic_auth(bearer: &HeaderValue) -> Result<DtzProfile, String> {
     match *cred_type {
         "apikey" => {
             let password = parts.get(1).unwrap_or(&"");
-            let result = ApiKeyId::try_from(*password);
-            match result {
-                Ok(key) => verify_api_key(&key, None).await,
-                Err(err) => Err(err),
-            }
+            let key = ApiKeyId::try_from(*password)?;
+            let mut profile = verify_api_key(&key, None).await?;
+            profile.auth_method = AuthMethod::Basic { api_key: Some(key) };
+            Ok(profile)
         }
         "bearer" => {
             let token = parts.get(1).unwrap_or(&"");
-            verify_token(token.to_string())
+            let mut profile = verify_token(token.to_string())?;
+            profile.auth_method = AuthMethod::Basic { api_key: None };
+            Ok(profile)
         }
         _ => Err(
             "invalid crendential type, please use the `user` to a valid value, e.g. apikey, bearer"
@@ -251,7 +514,9 @@ async fn verify_basic_auth(bearer: &HeaderValue) -> Result<DtzProfile, String> {
 /// retrieve the profile information from a bearer token
 pub fn get_profile_from_bearer(bearer: impl Into<String>) -> Result<DtzProfile, String> {
     let bearer_str = bearer.into();
-    verify_token(bearer_str)
+    let mut profile = verify_token(bearer_str)?;
+    profile.auth_method = AuthMethod::Bearer;
+    Ok(profile)
 }
 
 fn verify_token_from_bearer(bearer: HeaderValue) -> Result<DtzProfile, String> {
@@ -276,49 +541,7 @@ fn verify_token(token: String) -> Result<DtzProfile, String> {
                     .unwrap();
                 let json_str = String::from_utf8_lossy(&decoded);
                 let json: Value = serde_json::de::from_str(&json_str).unwrap();
-                let empty_arr = Value::Array(vec![]);
-                let roles_claim = json.get("roles").unwrap_or(&empty_arr);
-                let contexts_claim = json.get("contexts").unwrap_or(&empty_arr);
-                // process roles
-                let mut roles: Vec<String> = Vec::new();
-                let arr = roles_claim.as_array().unwrap();
-                for role in arr {
-                    roles.push(role.as_str().unwrap().to_string());
-                }
-                // process contexts
-                let mut contexts: Vec<ContextId> = Vec::new();
-                let arr = contexts_claim.as_array().unwrap();
-                for context in arr {
-                    contexts.push(ContextId::try_from(context.as_str().unwrap()).unwrap());
-                }
-                let scope_str = json.get("scope").unwrap().as_str().unwrap();
-                let subject_str = json.get("sub").unwrap().as_str().unwrap();
-                let identity = match IdentityId::try_from(subject_str) {
-                    Ok(id) => id,
-                    Err(_err) => match Uuid::parse_str(subject_str) {
-                        Ok(id) => IdentityId { id: id.to_string() },
-                        Err(_err) => {
-                            return Err("invalid token".to_string());
-                        }
-                    },
-                };
-                let context = match ContextId::try_from(scope_str) {
-                    Ok(id) => id,
-                    Err(_err) => match Uuid::parse_str(scope_str) {
-                        Ok(id) => ContextId { id: id.to_string() },
-                        Err(_err) => {
-                            return Err("invalid token".to_string());
-                        }
-                    },
-                };
-                let result = DtzProfile {
-                    identity_id: identity,
-                    context_id: context,
-                    roles,
-                    contexts,
-                    token,
-                };
-                Ok(result)
+                profile_from_claims(&json, token)
             }
             Err(_) => Err("invalid token".to_string()),
         }
@@ -328,6 +551,80 @@ fn verify_token(token: String) -> Result<DtzProfile, String> {
     }
 }
 
+/// builds the profile from the already verified claims of a token
+fn profile_from_claims(json: &Value, token: String) -> Result<DtzProfile, String> {
+    let empty_arr = Value::Array(vec![]);
+    let roles_claim = json.get("roles").unwrap_or(&empty_arr);
+    let contexts_claim = json.get("contexts").unwrap_or(&empty_arr);
+    // process roles
+    let mut roles: Vec<String> = Vec::new();
+    let arr = roles_claim.as_array().unwrap();
+    for role in arr {
+        roles.push(role.as_str().unwrap().to_string());
+    }
+    // process contexts
+    let mut contexts: Vec<ContextId> = Vec::new();
+    let arr = contexts_claim.as_array().unwrap();
+    for context in arr {
+        contexts.push(ContextId::try_from(context.as_str().unwrap()).unwrap());
+    }
+    let scope_str = json.get("scope").unwrap().as_str().unwrap();
+    let subject_str = json.get("sub").unwrap().as_str().unwrap();
+    let identity = match IdentityId::try_from(subject_str) {
+        Ok(id) => id,
+        Err(_err) => match Uuid::parse_str(subject_str) {
+            Ok(id) => IdentityId { id: id.to_string() },
+            Err(_err) => {
+                return Err("invalid token".to_string());
+            }
+        },
+    };
+    let context = match ContextId::try_from(scope_str) {
+        Ok(id) => id,
+        Err(_err) => match Uuid::parse_str(scope_str) {
+            Ok(id) => ContextId { id: id.to_string() },
+            Err(_err) => {
+                return Err("invalid token".to_string());
+            }
+        },
+    };
+    let result = DtzProfile {
+        identity_id: identity,
+        context_id: context,
+        roles,
+        contexts,
+        token,
+        issued_at: json.get("iat").and_then(Value::as_u64),
+        expires_at: json.get("exp").and_then(Value::as_u64),
+        token_id: json.get("jti").and_then(Value::as_str).map(String::from),
+        issuer: json.get("iss").and_then(Value::as_str).map(String::from),
+        audience: match json.get("aud") {
+            Some(Value::String(aud)) => Some(vec![aud.to_string()]),
+            Some(Value::Array(arr)) => Some(
+                arr.iter()
+                    .filter_map(Value::as_str)
+                    .map(String::from)
+                    .collect(),
+            ),
+            _ => None,
+        },
+        auth_method: AuthMethod::Unknown,
+        auth_time: json.get("auth_time").and_then(Value::as_u64),
+        acr: json.get("acr").and_then(Value::as_str).map(String::from),
+        amr: json
+            .get("amr")
+            .and_then(Value::as_array)
+            .map(|arr| {
+                arr.iter()
+                    .filter_map(Value::as_str)
+                    .map(String::from)
+                    .collect()
+            })
+            .unwrap_or_default(),
+    };
+    Ok(result)
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 struct TokenResponse {
     access_token: String,
@@ -416,21 +713,16 @@ async fn verify_api_key(
     }
 }
 
-fn replace_placeholder(template: &str, profile: &DtzProfile) -> String {
-    let mut result = template.to_string();
-    result = result.replace("{identity_id}", &profile.identity_id.to_string());
-    result = result.replace("{context_id}", &profile.context_id.to_string());
-    result = result.replace("{roles}", &profile.roles.join(","));
-    result
+fn replace_placeholder(template: &str, profile: &DtzProfile) -> Result<String, String> {
+    RoleTemplate::parse(template)?.render(profile, &RequestValues::default())
 }
 
 /// verifies the role on a given profile
 pub fn verify_role(profile: &DtzProfile, role: &str) -> bool {
-    profile.roles.contains(&role.to_string())
+    role::holds_role(&profile.roles, role)
 }
 
 /// verifies the role on a given profile within the current context
 pub fn verfify_context_role(profile: &DtzProfile, role: &str) -> bool {
-    let replaced_role = replace_placeholder(role, profile);
-    profile.roles.contains(&replaced_role)
+    profile.require(role)
 }
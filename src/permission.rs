@@ -0,0 +1,217 @@
+use crate::DtzProfile;
+use http::{Method, Request, header};
+use http_body_util::BodyExt;
+use hyper_rustls::HttpsConnector;
+use hyper_util::{
+    client::legacy::{Client, connect::HttpConnector},
+    rt::TokioExecutor,
+};
+use lru_time_cache::LruCache;
+use serde::Deserialize;
+use std::{future::Future, pin::Pin, sync::Mutex, time::Duration};
+
+/// future returned by [`PermissionChecker::check_permission`]
+pub type PermissionFuture<'a> = Pin<Box<dyn Future<Output = Result<bool, String>> + Send + 'a>>;
+
+/// checks fine-grained permissions which can't be expressed by roles, e.g. sharing of a single resource
+pub trait PermissionChecker: Send + Sync {
+    /// checks whether the identity of the profile has the relation to the object,
+    /// e.g. `viewer` of `container:1234`
+    ///
+    /// the future is boxed, so checkers can be shared as `Arc<dyn PermissionChecker>`
+    fn check_permission<'a>(
+        &'a self,
+        profile: &'a DtzProfile,
+        relation: &'a str,
+        object: &'a str,
+    ) -> PermissionFuture<'a>;
+}
+
+/// default endpoint of the identity service for permission checks
+const PERMISSION_ENDPOINT: &str = "https://identity.dtz.rocks/api/2021-02-21/auth/permission";
+
+/// [`PermissionChecker`] asking the identity service, decisions are cached locally
+///
+/// the check is sent with the token of the profile, so the identity service decides for the
+/// identity and context of the token
+pub struct IdentityPermissionChecker {
+    endpoint: String,
+    cache: Mutex<LruCache<String, bool>>,
+    connector: Option<HttpsConnector<HttpConnector>>,
+}
+
+impl std::fmt::Debug for IdentityPermissionChecker {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("IdentityPermissionChecker")
+            .field("endpoint", &self.endpoint)
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Deserialize)]
+struct PermissionResponse {
+    allowed: bool,
+}
+
+impl Default for IdentityPermissionChecker {
+    fn default() -> Self {
+        Self {
+            endpoint: PERMISSION_ENDPOINT.to_string(),
+            cache: Mutex::new(LruCache::with_expiry_duration_and_capacity(
+                Duration::from_secs(60),
+                1000,
+            )),
+            connector: None,
+        }
+    }
+}
+
+impl IdentityPermissionChecker {
+    /// creates a checker for the identity service, decisions are cached for one minute
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// uses a different endpoint of the identity service
+    pub fn with_endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        self.endpoint = endpoint.into();
+        self
+    }
+
+    /// caches up to `capacity` decisions for `ttl`
+    pub fn with_cache(mut self, ttl: Duration, capacity: usize) -> Self {
+        self.cache = Mutex::new(LruCache::with_expiry_duration_and_capacity(ttl, capacity));
+        self
+    }
+
+    /// uses the connector instead of the default https-only connector, e.g. to test against a
+    /// local http server
+    #[cfg(any(test, feature = "test-util"))]
+    pub fn with_connector(mut self, connector: HttpsConnector<HttpConnector>) -> Self {
+        self.connector = Some(connector);
+        self
+    }
+
+    async fn check(
+        &self,
+        profile: &DtzProfile,
+        relation: &str,
+        object: &str,
+    ) -> Result<bool, String> {
+        let req_data = serde_json::json!({
+            "identityId": profile.identity_id,
+            "contextId": profile.context_id,
+            "relation": relation,
+            "object": object,
+        })
+        .to_string();
+        if let Some(allowed) = self.cache.lock().unwrap().get(&req_data) {
+            return Ok(*allowed);
+        }
+        let hostname = std::env::var("HOSTNAME").unwrap_or_else(|_| "localhost".to_string());
+        let req = Request::builder()
+            .method(Method::POST)
+            .uri(&self.endpoint)
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(header::AUTHORIZATION, format!("Bearer {}", profile.token))
+            .header("X-DTZ-SOURCE", hostname)
+            .body(req_data.clone())
+            .map_err(|e| format!("invalid permission endpoint: {e}"))?;
+        let https = match &self.connector {
+            Some(connector) => connector.clone(),
+            None => hyper_rustls::HttpsConnectorBuilder::new()
+                .with_native_roots()
+                .map_err(|e| format!("failed to load root certificates: {e}"))?
+                .https_only()
+                .enable_http1()
+                .enable_http2()
+                .build(),
+        };
+        let http_client = Client::builder(TokioExecutor::new()).build(https);
+        let resp = http_client
+            .request(req)
+            .await
+            .map_err(|e| format!("permission check failed: {e}"))?;
+        if !resp.status().is_success() {
+            return Err(format!("permission check failed: {}", resp.status()));
+        }
+        let bytes = resp
+            .into_body()
+            .collect()
+            .await
+            .map_err(|e| format!("permission check failed: {e}"))?
+            .to_bytes();
+        let response: PermissionResponse = serde_json::from_slice(&bytes)
+            .map_err(|e| format!("invalid permission response: {e}"))?;
+        self.cache
+            .lock()
+            .unwrap()
+            .insert(req_data, response.allowed);
+        Ok(response.allowed)
+    }
+}
+
+impl PermissionChecker for IdentityPermissionChecker {
+    fn check_permission<'a>(
+        &'a self,
+        profile: &'a DtzProfile,
+        relation: &'a str,
+        object: &'a str,
+    ) -> PermissionFuture<'a> {
+        Box::pin(self.check(profile, relation, object))
+    }
+}
+
+/// [`PermissionChecker`] for tests, only grants the configured permissions
+///
+/// only available in tests and with the `test-util` feature
+/// ```ignore
+/// use dtz_identity_auth::FakePermissionChecker;
+/// let checker = FakePermissionChecker::new().grant("viewer", "container:1234");
+/// ```
+#[cfg(any(test, feature = "test-util"))]
+#[derive(Debug, Clone, Default)]
+pub struct FakePermissionChecker {
+    granted: Vec<(Option<String>, String, String)>,
+}
+
+#[cfg(any(test, feature = "test-util"))]
+impl FakePermissionChecker {
+    /// creates a checker which denies everything
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// grants the relation to the object for every identity
+    pub fn grant(mut self, relation: &str, object: &str) -> Self {
+        self.granted
+            .push((None, relation.to_string(), object.to_string()));
+        self
+    }
+
+    /// grants the relation to the object for a single identity
+    pub fn grant_to(mut self, identity_id: &str, relation: &str, object: &str) -> Self {
+        self.granted.push((
+            Some(identity_id.to_string()),
+            relation.to_string(),
+            object.to_string(),
+        ));
+        self
+    }
+}
+
+#[cfg(any(test, feature = "test-util"))]
+impl PermissionChecker for FakePermissionChecker {
+    fn check_permission<'a>(
+        &'a self,
+        profile: &'a DtzProfile,
+        relation: &'a str,
+        object: &'a str,
+    ) -> PermissionFuture<'a> {
+        let identity_id = profile.identity_id.to_string();
+        let granted = self.granted.iter().any(|(identity, r, o)| {
+            identity.as_ref().is_none_or(|i| *i == identity_id) && r == relation && o == object
+        });
+        Box::pin(std::future::ready(Ok(granted)))
+    }
+}
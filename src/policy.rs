@@ -0,0 +1,79 @@
+use crate::{DtzProfile, RequestValues, attribute, template};
+use dtz_identity_auth_policy::Expr;
+use std::{fmt::Display, str::FromStr};
+
+/// parsed policy expression over role templates
+///
+/// a policy combines role templates (see [`DtzProfile::require`]) with `AND`, `OR`, `NOT` and
+/// parentheses, e.g. `https://dtz.rocks/containers/admin/{context_id} OR https://dtz.rocks/admin`.
+/// `&&`, `||` and `!` can be used as well, `AND` binds stronger than `OR`.
+/// role templates containing whitespace or parentheses can be quoted with `"`.
+/// the role templates are parsed as [`RoleTemplate`](crate::RoleTemplate), so unknown
+/// placeholders are rejected.
+///
+/// attributes of the profile can be compared with `==`, `!=`, `<`, `<=`, `>`, `>=` and `in`,
+/// e.g. `claim.plan in ["pro", "enterprise"] AND contexts.len() < 10`. supported attributes
+/// are `identity_id`, `context_id`, `contexts.len()`, `roles.len()` and claims of the token as
+/// `claim.<name>`, other attributes are rejected. operators are separated by whitespace.
+/// values are strings, numbers or booleans, strings need to be quoted if they contain
+/// whitespace. comparisons of missing claims are always `false`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Policy {
+    expr: Expr,
+}
+
+impl Policy {
+    /// parses the policy expression
+    pub fn parse(expression: &str) -> Result<Policy, String> {
+        let expr = dtz_identity_auth_policy::parse(expression)?;
+        Ok(Policy { expr })
+    }
+
+    /// evaluates the policy against the profile
+    ///
+    /// role templates with path or query placeholders are not granted, see [`Policy::evaluate_with`]
+    pub fn evaluate(&self, profile: &DtzProfile) -> bool {
+        evaluate(&self.expr, profile, &RequestValues::default())
+    }
+
+    pub(crate) fn expr(&self) -> &Expr {
+        &self.expr
+    }
+
+    /// evaluates the policy against the profile, using the values of the request for role templates
+    pub fn evaluate_with(&self, profile: &DtzProfile, values: &RequestValues) -> bool {
+        evaluate(&self.expr, profile, values)
+    }
+}
+
+impl FromStr for Policy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Policy::parse(s)
+    }
+}
+
+impl Display for Policy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.expr.fmt(f)
+    }
+}
+
+/// evaluates the expression against the profile and the values of the request
+pub(crate) fn evaluate(expr: &Expr, profile: &DtzProfile, values: &RequestValues) -> bool {
+    match expr {
+        Expr::Role(role) => template::is_granted(role, profile, values),
+        Expr::Compare(comparison) => attribute::evaluate(comparison, profile),
+        Expr::Not(expr) => !evaluate(expr, profile, values),
+        Expr::And(exprs) => exprs.iter().all(|e| evaluate(e, profile, values)),
+        Expr::Or(exprs) => exprs.iter().any(|e| evaluate(e, profile, values)),
+    }
+}
+
+impl DtzProfile {
+    /// checks the profile against a parsed [`Policy`]
+    pub fn satisfies(&self, policy: &Policy) -> bool {
+        policy.evaluate(self)
+    }
+}
@@ -0,0 +1,108 @@
+use axum::extract::OriginalUri;
+use http::{header, request::Parts};
+
+/// default login page of DownToZero
+const DEFAULT_LOGIN_URL: &str = "https://dtz.rocks/login";
+
+/// redirect of browser requests to the login page, see [`AuthConfig::login_redirect`](crate::AuthConfig::login_redirect)
+///
+/// unauthenticated requests accepting `text/html` are redirected with `302 Found` to the login page,
+/// the url of the request is passed as `return_to` parameter if its host is allowed. other clients
+/// still get `401 Unauthorized`.
+/// ```
+/// use dtz_identity_auth::LoginRedirect;
+/// let redirect = LoginRedirect::new().allow_host("app.dtz.rocks");
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoginRedirect {
+    login_url: String,
+    allowed_hosts: Vec<String>,
+}
+
+impl Default for LoginRedirect {
+    fn default() -> Self {
+        Self {
+            login_url: DEFAULT_LOGIN_URL.to_string(),
+            allowed_hosts: Vec::new(),
+        }
+    }
+}
+
+impl LoginRedirect {
+    /// redirects to the DownToZero login page, without allowed hosts for `return_to`
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// redirects to a different login page
+    pub fn login_url(mut self, url: impl Into<String>) -> Self {
+        self.login_url = url.into();
+        self
+    }
+
+    /// allows the host (including the port, if any) as target of `return_to`
+    pub fn allow_host(mut self, host: impl Into<String>) -> Self {
+        self.allowed_hosts.push(host.into().to_ascii_lowercase());
+        self
+    }
+
+    /// location to redirect the request to, `None` if the request does not accept html
+    pub(crate) fn location(&self, req: &Parts) -> Option<String> {
+        if !accepts_html(req) {
+            return None;
+        }
+        let Some(return_to) = self.return_to(req) else {
+            return Some(self.login_url.clone());
+        };
+        let separator = if self.login_url.contains('?') {
+            '&'
+        } else {
+            '?'
+        };
+        let query = serde_urlencoded::to_string([("return_to", return_to)]).ok()?;
+        Some(format!("{}{separator}{query}", self.login_url))
+    }
+
+    /// url of the request, only if its host is allowed
+    ///
+    /// nested routers see the uri without the nest prefix, so the original uri is used
+    fn return_to(&self, req: &Parts) -> Option<String> {
+        let uri = match req.extensions.get::<OriginalUri>() {
+            Some(OriginalUri(uri)) => uri,
+            None => &req.uri,
+        };
+        let host = match uri.authority() {
+            Some(authority) => authority.as_str().to_string(),
+            None => req.headers.get(header::HOST)?.to_str().ok()?.to_string(),
+        };
+        let host = host.to_ascii_lowercase();
+        if !self.allowed_hosts.contains(&host) || !is_valid_host(&host) {
+            return None;
+        }
+        let path = uri.path_and_query().map(|p| p.as_str()).unwrap_or("/");
+        // `//` would turn the path into a different host
+        if !path.starts_with('/') || path.starts_with("//") {
+            return None;
+        }
+        Some(format!("https://{host}{path}"))
+    }
+}
+
+fn accepts_html(req: &Parts) -> bool {
+    req.headers
+        .get_all(header::ACCEPT)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|media_type| {
+            let media_type = media_type.split(';').next().unwrap_or_default().trim();
+            media_type.eq_ignore_ascii_case("text/html")
+        })
+}
+
+fn is_valid_host(host: &str) -> bool {
+    !host.is_empty()
+        && host
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-' || c == ':')
+}
@@ -0,0 +1,245 @@
+use once_cell::sync::Lazy;
+use std::{collections::HashMap, fmt::Display, str::FromStr, sync::RwLock};
+
+static ROLE_GRAPH: Lazy<RwLock<RoleGraph>> = Lazy::new(|| RwLock::new(RoleGraph::default()));
+
+/// structured role of the form `https://dtz.rocks/{service}/{permission}/{resource}`
+///
+/// a granted role implies a required role if it matches the required role segment by segment,
+/// where `*` matches any single segment. a granted role which is shorter than the required role
+/// implies all roles below it, e.g. `https://dtz.rocks/containers/admin/*` implies
+/// `https://dtz.rocks/containers/admin/context-12345678` and
+/// `https://dtz.rocks/containers` implies every containers role.
+///
+/// role checks only use these implications if enabled with [`RoleGraph::hierarchical`],
+/// otherwise granted roles have to match exactly.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Role {
+    origin: String,
+    segments: Vec<String>,
+}
+
+/// segment matching any value
+const WILDCARD: &str = "*";
+
+impl Role {
+    /// parses a role in url form, a trailing `/` is ignored and empty segments are rejected
+    pub fn parse(role: &str) -> Result<Role, String> {
+        let Some((scheme, rest)) = role.split_once("://") else {
+            return Err(format!("role `{role}` is not an url"));
+        };
+        let (host, path) = rest.split_once('/').unwrap_or((rest, ""));
+        if scheme.is_empty() || host.is_empty() {
+            return Err(format!("role `{role}` is not an url"));
+        }
+        let path = path.strip_suffix('/').unwrap_or(path);
+        if path.is_empty() {
+            return Err(format!("role `{role}` has no service"));
+        }
+        let segments: Vec<String> = path.split('/').map(String::from).collect();
+        if segments.iter().any(String::is_empty) {
+            return Err(format!("role `{role}` has an empty segment"));
+        }
+        Ok(Role {
+            origin: format!("{scheme}://{host}"),
+            segments,
+        })
+    }
+
+    /// scheme and host of the role, e.g. `https://dtz.rocks`
+    pub fn origin(&self) -> &str {
+        &self.origin
+    }
+
+    /// all path segments of the role
+    pub fn segments(&self) -> &[String] {
+        &self.segments
+    }
+
+    /// service of the role, e.g. `containers`
+    pub fn service(&self) -> &str {
+        &self.segments[0]
+    }
+
+    /// permission of the role, e.g. `admin`
+    pub fn permission(&self) -> Option<&str> {
+        self.segments.get(1).map(String::as_str)
+    }
+
+    /// resource of the role, e.g. the context id
+    pub fn resource(&self) -> Option<String> {
+        if self.segments.len() > 2 {
+            Some(self.segments[2..].join("/"))
+        } else {
+            None
+        }
+    }
+
+    /// creates the same role with a different permission
+    pub fn with_permission(&self, permission: &str) -> Role {
+        let mut role = self.clone();
+        if role.segments.len() > 1 {
+            role.segments[1] = permission.to_string();
+        } else {
+            role.segments.push(permission.to_string());
+        }
+        role
+    }
+
+    /// checks whether this (granted) role implies the required role
+    pub fn implies(&self, required: &Role) -> bool {
+        self.origin == required.origin
+            && self.segments.len() <= required.segments.len()
+            && self
+                .segments
+                .iter()
+                .zip(&required.segments)
+                .all(|(granted, required)| granted == WILDCARD || granted == required)
+    }
+}
+
+impl FromStr for Role {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Role::parse(s)
+    }
+}
+
+impl Display for Role {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.origin, self.segments.join("/"))
+    }
+}
+
+/// checks whether any of the granted roles, expanded by the installed role graph, implies the required role
+pub(crate) fn holds_role(granted: &[String], required: &str) -> bool {
+    ROLE_GRAPH.read().unwrap().holds(granted, required)
+}
+
+/// service local implications between permissions, e.g. `admin` implies `write` implies `read`
+///
+/// the graph is installed with [`set_role_graph`] and consulted by all role checks.
+/// wildcard and prefix matching of granted roles (see [`Role`]) is enabled with [`RoleGraph::hierarchical`].
+/// ```
+/// use dtz_identity_auth::RoleGraph;
+/// let graph = RoleGraph::new()
+///     .imply("containers", "admin", "write")?
+///     .imply("containers", "write", "read")?;
+/// # Ok::<(), String>(())
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct RoleGraph {
+    implications: HashMap<String, HashMap<String, Vec<String>>>,
+    hierarchical: bool,
+}
+
+impl RoleGraph {
+    /// creates an empty graph
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// granted roles imply the roles below them and `*` matches any segment, see [`Role::implies`]
+    pub fn hierarchical(mut self) -> Self {
+        self.hierarchical = true;
+        self
+    }
+
+    /// declares that `permission` implies `implied` within `service`
+    ///
+    /// fails if the implication would introduce a cycle
+    pub fn imply(mut self, service: &str, permission: &str, implied: &str) -> Result<Self, String> {
+        if permission == implied
+            || self
+                .implied_permissions(service, implied)
+                .iter()
+                .any(|p| p == permission)
+        {
+            return Err(format!(
+                "implication {permission} => {implied} of service {service} introduces a cycle"
+            ));
+        }
+        let implied_by = self
+            .implications
+            .entry(service.to_string())
+            .or_default()
+            .entry(permission.to_string())
+            .or_default();
+        if !implied_by.iter().any(|p| p == implied) {
+            implied_by.push(implied.to_string());
+        }
+        Ok(self)
+    }
+
+    /// all permissions transitively implied by the permission within the service
+    pub fn implied_permissions(&self, service: &str, permission: &str) -> Vec<String> {
+        let mut result: Vec<String> = Vec::new();
+        let Some(service) = self.implications.get(service) else {
+            return result;
+        };
+        let mut pending = vec![permission];
+        while let Some(current) = pending.pop() {
+            for implied in service.get(current).into_iter().flatten() {
+                if !result.contains(implied) {
+                    result.push(implied.clone());
+                    pending.push(implied);
+                }
+            }
+        }
+        result
+    }
+
+    /// checks whether a granted role implies the required role
+    ///
+    /// roles match exactly unless the graph is hierarchical, roles which are not in url form always match exactly
+    pub fn implies(&self, granted: &str, required: &str) -> bool {
+        if granted == required {
+            return true;
+        }
+        if !self.hierarchical {
+            return false;
+        }
+        match (Role::parse(granted), Role::parse(required)) {
+            (Ok(granted), Ok(required)) => granted.implies(&required),
+            _ => false,
+        }
+    }
+
+    /// checks whether any of the granted roles, expanded by the graph, implies the required role
+    pub fn holds(&self, granted: &[String], required: &str) -> bool {
+        self.expand(granted)
+            .iter()
+            .any(|role| self.implies(role, required))
+    }
+
+    /// expands the granted roles with all implied roles
+    pub fn expand(&self, roles: &[String]) -> Vec<String> {
+        let mut result = roles.to_vec();
+        for role in roles {
+            let Ok(parsed) = Role::parse(role) else {
+                continue;
+            };
+            let Some(permission) = parsed.permission() else {
+                continue;
+            };
+            for implied in self.implied_permissions(parsed.service(), permission) {
+                let implied = parsed.with_permission(&implied).to_string();
+                if !result.contains(&implied) {
+                    result.push(implied);
+                }
+            }
+        }
+        result
+    }
+}
+
+/// installs the role graph used by all role checks
+pub fn set_role_graph(graph: RoleGraph) {
+    *ROLE_GRAPH.write().unwrap() = graph;
+}
+
+/// expands the granted roles with the installed role graph
+pub(crate) fn expand_roles(roles: &[String]) -> Vec<String> {
+    ROLE_GRAPH.read().unwrap().expand(roles)
+}
@@ -0,0 +1,376 @@
+use crate::{AuthConfig, token_claims, verify_token};
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+};
+use axum_core::response::{IntoResponseParts, Response, ResponseParts};
+use cookie::{Cookie, SameSite, time};
+use http::{HeaderValue, header, request::Parts};
+use serde_json::Value;
+use std::{
+    convert::Infallible,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// name of the cookie carrying the token
+pub(crate) const AUTH_COOKIE: &str = "dtz-auth";
+
+/// default maximum size of a cookie value, browsers limit cookies to about 4096 bytes
+const DEFAULT_CHUNK_SIZE: usize = 3800;
+
+/// smallest chunk size, keeps the number of chunk cookies bounded
+const MIN_CHUNK_SIZE: usize = 100;
+
+/// largest token whose chunks are all removed on logout
+const MAX_TOKEN_SIZE: usize = 32 * 1024;
+
+/// builds the `dtz-auth` cookie to log in and out
+///
+/// the cookie is `Secure`, `HttpOnly` and `SameSite=Lax` by default and expires with the token.
+/// cookies named with the `__Host-` or `__Secure-` prefix get the attributes the prefix requires.
+/// tokens larger than the [chunk size](AuthCookie::chunk_size) are split across `dtz-auth.0`, `dtz-auth.1`, …
+/// ```
+/// use dtz_identity_auth::{AuthCookie, SameSite};
+/// let cookies = AuthCookie::new()
+///     .domain("dtz.rocks")
+///     .same_site(SameSite::Strict);
+/// let logout = cookies.logout();
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuthCookie {
+    name: String,
+    domain: Option<String>,
+    path: String,
+    same_site: SameSite,
+    secure: bool,
+    http_only: bool,
+    chunk_size: usize,
+}
+
+impl Default for AuthCookie {
+    fn default() -> Self {
+        Self {
+            name: AUTH_COOKIE.to_string(),
+            domain: None,
+            path: "/".to_string(),
+            same_site: SameSite::Lax,
+            secure: true,
+            http_only: true,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+        }
+    }
+}
+
+impl AuthCookie {
+    /// creates the builder with the secure defaults
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// name of the cookie, `dtz-auth` by default, see [`AuthConfig::cookie_name`]
+    ///
+    /// `__Host-` cookies are always `Secure`, host only and on path `/`,
+    /// `__Secure-` cookies are always `Secure`
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    /// domain of the cookie, the cookie is host only without domain
+    pub fn domain(mut self, domain: impl Into<String>) -> Self {
+        self.domain = Some(domain.into());
+        self
+    }
+
+    /// path of the cookie, `/` by default
+    pub fn path(mut self, path: impl Into<String>) -> Self {
+        self.path = path.into();
+        self
+    }
+
+    /// `SameSite` attribute of the cookie, `Lax` by default
+    pub fn same_site(mut self, same_site: SameSite) -> Self {
+        self.same_site = same_site;
+        self
+    }
+
+    /// `Secure` attribute of the cookie, only disable it for local development
+    pub fn secure(mut self, secure: bool) -> Self {
+        self.secure = secure;
+        self
+    }
+
+    /// `HttpOnly` attribute of the cookie
+    pub fn http_only(mut self, http_only: bool) -> Self {
+        self.http_only = http_only;
+        self
+    }
+
+    /// maximum size of the cookie value, larger tokens are split into chunks, 3800 bytes by default
+    ///
+    /// the chunk size is at least 100 bytes, [`logout`](AuthCookie::logout) removes the chunks
+    /// of tokens up to 32 KiB
+    pub fn chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size.max(MIN_CHUNK_SIZE);
+        self
+    }
+
+    /// cookie carrying the token, `Max-Age` is the remaining lifetime of the token (`exp` claim)
+    ///
+    /// tokens without `exp` claim are issued as session cookie. chunked tokens remove the
+    /// unchunked cookie and the chunk after the last one, so stale chunks are not reassembled.
+    pub fn issue(&self, token: &str) -> SetCookies {
+        let max_age = token_claims(token)
+            .get("exp")
+            .and_then(Value::as_u64)
+            .map(|exp| {
+                let now = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs();
+                time::Duration::seconds(exp.saturating_sub(now).min(i64::MAX as u64) as i64)
+            });
+        if token.len() <= self.chunk_size {
+            let mut cookie = self.cookie(self.name.clone(), token.to_string());
+            if let Some(max_age) = max_age {
+                cookie.set_max_age(max_age);
+            }
+            return SetCookies {
+                cookies: vec![cookie],
+            };
+        }
+        // chunks end on a character boundary, the chunk size is larger than any character
+        let mut chunks = Vec::new();
+        let mut rest = token;
+        while !rest.is_empty() {
+            let mut end = self.chunk_size.min(rest.len());
+            while !rest.is_char_boundary(end) {
+                end -= 1;
+            }
+            let (chunk, tail) = rest.split_at(end);
+            chunks.push(chunk);
+            rest = tail;
+        }
+        let mut cookies: Vec<Cookie<'static>> = chunks
+            .iter()
+            .enumerate()
+            .map(|(index, chunk)| {
+                let mut cookie = self.cookie(chunk_name(&self.name, index), chunk.to_string());
+                if let Some(max_age) = max_age {
+                    cookie.set_max_age(max_age);
+                }
+                cookie
+            })
+            .collect();
+        cookies.push(self.removal(chunk_name(&self.name, chunks.len())));
+        cookies.push(self.removal(self.name.clone()));
+        SetCookies { cookies }
+    }
+
+    /// cookies removing the token and its chunks from the browser
+    ///
+    /// without the request the chunks can't be known, so every chunk a token up to 32 KiB could
+    /// have is removed. prefer [`logout_for`](AuthCookie::logout_for) in handlers.
+    pub fn logout(&self) -> SetCookies {
+        let chunks = MAX_TOKEN_SIZE.div_ceil(self.chunk_size);
+        let cookies = std::iter::once(self.name.clone())
+            .chain((0..chunks).map(|index| chunk_name(&self.name, index)))
+            .map(|name| self.removal(name))
+            .collect();
+        SetCookies { cookies }
+    }
+
+    /// cookies removing the token and the chunks the request carries
+    /// ```ignore
+    /// async fn logout(parts: Parts) -> impl IntoResponse {
+    ///     (AuthCookie::new().logout_for(&parts), Redirect::to("/"))
+    /// }
+    /// ```
+    pub fn logout_for(&self, req: &Parts) -> SetCookies {
+        let cookies = present_cookies(&request_cookies(req), &self.name)
+            .into_iter()
+            .map(|name| self.removal(name))
+            .collect();
+        SetCookies { cookies }
+    }
+
+    fn removal(&self, name: String) -> Cookie<'static> {
+        let mut cookie = self.cookie(name, String::new());
+        cookie.make_removal();
+        cookie
+    }
+
+    fn cookie(&self, name: String, value: String) -> Cookie<'static> {
+        let host_prefix = self.name.starts_with(HOST_PREFIX);
+        let secure_prefix = host_prefix || self.name.starts_with(SECURE_PREFIX);
+        let mut cookie = Cookie::build((name, value))
+            .path(if host_prefix { "/" } else { &self.path }.to_string())
+            .same_site(self.same_site)
+            .secure(self.secure || secure_prefix)
+            .http_only(self.http_only)
+            .build();
+        if let Some(domain) = &self.domain
+            && !host_prefix
+        {
+            cookie.set_domain(domain.clone());
+        }
+        cookie
+    }
+}
+
+/// name of a chunk of the cookie, e.g. `dtz-auth.0`
+fn chunk_name(name: &str, index: usize) -> String {
+    format!("{name}.{index}")
+}
+
+/// the cookie and its chunks present in the request, always contains the unchunked name
+fn present_cookies(cookies: &[Cookie], name: &str) -> Vec<String> {
+    let mut names = vec![name.to_string()];
+    for cookie in cookies {
+        let is_chunk = cookie
+            .name()
+            .strip_prefix(name)
+            .and_then(|suffix| suffix.strip_prefix('.'))
+            .is_some_and(|index| index.parse::<usize>().is_ok());
+        if is_chunk && !names.iter().any(|n| n == cookie.name()) {
+            names.push(cookie.name().to_string());
+        }
+    }
+    names
+}
+
+/// cookies of all `Cookie` headers of the request
+fn request_cookies(req: &Parts) -> Vec<Cookie<'_>> {
+    req.headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| Cookie::split_parse(value).flatten())
+        .collect()
+}
+
+/// value of the cookie, chunks (`name.0`, `name.1`, …) are reassembled in order
+///
+/// the unchunked cookie takes precedence, the last cookie of a name wins
+pub(crate) fn cookie_value(cookies: &[Cookie], name: &str) -> Option<String> {
+    let find = |name: &str| {
+        cookies
+            .iter()
+            .rev()
+            .find(|c| c.name() == name)
+            .map(|c| c.value())
+    };
+    if let Some(value) = find(name) {
+        return Some(value.to_string());
+    }
+    let mut value = String::new();
+    let mut index = 0;
+    while let Some(chunk) = find(&chunk_name(name, index)) {
+        value.push_str(chunk);
+        index += 1;
+    }
+    (index > 0).then_some(value)
+}
+
+/// prefix of cookies which are bound to the host
+const HOST_PREFIX: &str = "__Host-";
+
+/// prefix of cookies which are only set over https
+const SECURE_PREFIX: &str = "__Secure-";
+
+/// middleware moving browsers from a legacy cookie name to the current one,
+/// to be used with `axum::middleware::from_fn_with_state`
+/// ```ignore
+/// set_auth_config(AuthConfig::new().cookie_name("__Host-dtz-auth").legacy_cookie_name("dtz-auth"));
+/// let cookies = AuthCookie::new().name("__Host-dtz-auth");
+/// let app = Router::new()
+///     .route("/", get(handler))
+///     .layer(middleware::from_fn_with_state(cookies, migrate_legacy_cookies));
+/// ```
+/// requests which only carry a valid token in a cookie with one of the
+/// [legacy names](AuthConfig::legacy_cookie_name) get the token reissued with the name of the
+/// [`AuthCookie`], the legacy cookies and their chunks are removed with the same domain and path.
+pub async fn migrate_legacy_cookies(
+    State(cookies): State<AuthCookie>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let (parts, body) = req.into_parts();
+    let migration = legacy_token(&parts, &cookies.name);
+    let mut response = next.run(Request::from_parts(parts, body)).await;
+    if let Some((names, token)) = migration {
+        let mut set_cookies = cookies.issue(&token);
+        for name in names {
+            // chunks share the prefix of the legacy name, so they get the same attributes
+            let legacy = cookies.clone().name(name.clone());
+            set_cookies.cookies.push(legacy.removal(name));
+        }
+        for value in set_cookies.header_values() {
+            response.headers_mut().append(header::SET_COOKIE, value);
+        }
+    }
+    response
+}
+
+/// legacy cookies and chunks present in the request and the valid token of the preferred one,
+/// `None` if the current cookie is present
+fn legacy_token(req: &Parts, current: &str) -> Option<(Vec<String>, String)> {
+    let config = AuthConfig::for_request(req);
+    let cookies = request_cookies(req);
+    if cookie_value(&cookies, current).is_some() {
+        return None;
+    }
+    let present: Vec<String> = config
+        .legacy_cookie_names()
+        .iter()
+        .filter(|name| *name != current && cookie_value(&cookies, name).is_some())
+        .cloned()
+        .collect();
+    let token = present.iter().find_map(|name| {
+        let token = cookie_value(&cookies, name)?;
+        verify_token(token.clone()).ok()?;
+        Some(token)
+    })?;
+    let removals = present
+        .iter()
+        .flat_map(|name| present_cookies(&cookies, name))
+        .collect();
+    Some((removals, token))
+}
+
+/// cookies to set on the response, adds one `Set-Cookie` header per cookie
+/// ```ignore
+/// async fn login(token: String) -> impl IntoResponse {
+///     (AuthCookie::new().issue(&token), Redirect::to("/"))
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct SetCookies {
+    cookies: Vec<Cookie<'static>>,
+}
+
+impl SetCookies {
+    /// the cookies
+    pub fn cookies(&self) -> &[Cookie<'static>] {
+        &self.cookies
+    }
+
+    /// values of the `Set-Cookie` headers
+    pub fn header_values(&self) -> Vec<HeaderValue> {
+        self.cookies
+            .iter()
+            .filter_map(|cookie| HeaderValue::from_str(&cookie.to_string()).ok())
+            .collect()
+    }
+}
+
+impl IntoResponseParts for SetCookies {
+    type Error = Infallible;
+
+    fn into_response_parts(self, mut res: ResponseParts) -> Result<ResponseParts, Self::Error> {
+        for value in self.header_values() {
+            res.headers_mut().append(header::SET_COOKIE, value);
+        }
+        Ok(res)
+    }
+}
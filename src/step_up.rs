@@ -0,0 +1,197 @@
+use crate::{AuthError, AuthRejection, DtzProfile, error::quote};
+use axum_core::extract::FromRequestParts;
+use http::request::Parts;
+use std::{
+    marker::PhantomData,
+    ops::Deref,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// minimum authentication strength based on the `acr`, `amr` and `auth_time` claims
+///
+/// requirements which are not met are rejected with `401 Unauthorized` and an
+/// `insufficient_user_authentication` challenge (RFC 9470), so the client can re-authenticate
+/// ```
+/// use dtz_identity_auth::StepUp;
+/// use std::time::Duration;
+/// // mfa within the last 10 minutes
+/// let step_up = StepUp::new().amr("mfa").max_age(Duration::from_secs(600));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StepUp {
+    acr_values: Vec<String>,
+    amr: Vec<String>,
+    max_age: Option<Duration>,
+}
+
+impl StepUp {
+    /// creates a requirement which accepts every authentication
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// accepts the authentication context class, if any are given the `acr` claim needs to match one of them
+    pub fn acr(mut self, acr: impl Into<String>) -> Self {
+        self.acr_values.push(acr.into());
+        self
+    }
+
+    /// requires the authentication method in the `amr` claim, e.g. `mfa` or `otp`
+    pub fn amr(mut self, method: impl Into<String>) -> Self {
+        self.amr.push(method.into());
+        self
+    }
+
+    /// requires the authentication (`auth_time` claim) to be at most `max_age` ago
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    /// checks the profile, the error describes the first requirement which is not met
+    pub fn check(&self, profile: &DtzProfile) -> Result<(), String> {
+        if !self.acr_values.is_empty()
+            && !profile
+                .acr
+                .as_ref()
+                .is_some_and(|acr| self.acr_values.contains(acr))
+        {
+            return Err(format!(
+                "authentication context class {} required",
+                self.acr_values.join(" or ")
+            ));
+        }
+        if let Some(method) = self.amr.iter().find(|m| !profile.amr.contains(m)) {
+            return Err(format!("authentication method {method} required"));
+        }
+        if let Some(max_age) = self.max_age {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs();
+            match profile.auth_time {
+                Some(auth_time) if now.saturating_sub(auth_time) <= max_age.as_secs() => {}
+                _ => {
+                    return Err(format!(
+                        "authentication within the last {} seconds required",
+                        max_age.as_secs()
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// `insufficient_user_authentication` challenge of the requirement (RFC 9470)
+    pub(crate) fn challenge_header(&self, realm: &str, description: &str) -> String {
+        let mut challenge = format!(
+            "Bearer realm=\"{realm}\", error=\"insufficient_user_authentication\", error_description=\"{}\"",
+            quote(description)
+        );
+        if !self.acr_values.is_empty() {
+            challenge.push_str(&format!(
+                ", acr_values=\"{}\"",
+                quote(&self.acr_values.join(" "))
+            ));
+        }
+        if let Some(max_age) = self.max_age {
+            challenge.push_str(&format!(", max_age={}", max_age.as_secs()));
+        }
+        challenge
+    }
+}
+
+impl DtzProfile {
+    /// checks whether the authentication of the profile meets the [`StepUp`] requirement
+    pub fn meets(&self, step_up: &StepUp) -> bool {
+        step_up.check(self).is_ok()
+    }
+}
+
+/// step-up requirement of a route, used with [`StepUpProfile`]
+pub trait StepUpRequirement {
+    /// the requirement
+    fn step_up() -> StepUp;
+}
+
+/// profile whose authentication meets the requirement `R`
+/// ```
+/// use dtz_identity_auth::{StepUp, StepUpProfile, StepUpRequirement};
+/// use std::time::Duration;
+///
+/// struct RecentMfa;
+///
+/// impl StepUpRequirement for RecentMfa {
+///     fn step_up() -> StepUp {
+///         StepUp::new().amr("mfa").max_age(Duration::from_secs(600))
+///     }
+/// }
+///
+/// async fn delete_account(profile: StepUpProfile<RecentMfa>) {}
+/// ```
+pub struct StepUpProfile<R> {
+    profile: DtzProfile,
+    requirement: PhantomData<fn() -> R>,
+}
+
+// implemented by hand, so the requirement marker doesn't need to implement the traits
+impl<R> Clone for StepUpProfile<R> {
+    fn clone(&self) -> Self {
+        Self {
+            profile: self.profile.clone(),
+            requirement: PhantomData,
+        }
+    }
+}
+
+impl<R> std::fmt::Debug for StepUpProfile<R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StepUpProfile")
+            .field("profile", &self.profile)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<R> StepUpProfile<R> {
+    /// the authenticated profile
+    pub fn profile(&self) -> &DtzProfile {
+        &self.profile
+    }
+
+    /// the authenticated profile
+    pub fn into_profile(self) -> DtzProfile {
+        self.profile
+    }
+}
+
+impl<R> Deref for StepUpProfile<R> {
+    type Target = DtzProfile;
+
+    fn deref(&self) -> &Self::Target {
+        &self.profile
+    }
+}
+
+impl<S, R> FromRequestParts<S> for StepUpProfile<R>
+where
+    S: Send + Sync,
+    R: StepUpRequirement,
+{
+    type Rejection = AuthRejection;
+
+    async fn from_request_parts(req: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let profile = DtzProfile::from_request_parts(req, state).await?;
+        let step_up = R::step_up();
+        if let Err(description) = step_up.check(&profile) {
+            let error = AuthError::InsufficientUserAuthentication {
+                step_up,
+                description,
+            };
+            return Err(AuthRejection::new(error, req));
+        }
+        Ok(StepUpProfile {
+            profile,
+            requirement: PhantomData,
+        })
+    }
+}
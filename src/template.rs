@@ -0,0 +1,184 @@
+use crate::DtzProfile;
+use axum::extract::RawPathParams;
+use axum_core::extract::FromRequestParts;
+use dtz_identity_auth_policy::{Part, Placeholder, Template};
+use http::request::Parts;
+use serde_json::Value;
+use std::{collections::HashMap, convert::Infallible, fmt::Display, str::FromStr};
+
+/// compiled role template
+///
+/// the template supports the following placeholders:
+/// - `{identity_id}`, `{context_id}` and `{roles}` of the profile
+/// - `{path.<name>}` for path parameters of the matched route
+/// - `{query.<name>}` for query parameters
+/// - `{claim.<name>}` for claims of the token
+///
+/// values of path, query and claim placeholders are percent-encoded, so they can't
+/// add additional segments to the role. unknown placeholders fail when parsing the template.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RoleTemplate {
+    template: Template,
+}
+
+impl RoleTemplate {
+    /// parses the template, unknown placeholders are rejected
+    pub fn parse(template: &str) -> Result<RoleTemplate, String> {
+        Ok(RoleTemplate {
+            template: Template::parse(template)?,
+        })
+    }
+
+    /// the template as written
+    pub fn as_str(&self) -> &str {
+        self.template.as_str()
+    }
+
+    /// substitutes all placeholders, fails if a value is not available
+    pub fn render(&self, profile: &DtzProfile, values: &RequestValues) -> Result<String, String> {
+        render(&self.template, profile, values)
+    }
+
+    /// checks whether the profile holds the rendered role
+    pub fn is_granted(&self, profile: &DtzProfile, values: &RequestValues) -> bool {
+        is_granted(&self.template, profile, values)
+    }
+}
+
+impl FromStr for RoleTemplate {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        RoleTemplate::parse(s)
+    }
+}
+
+impl Display for RoleTemplate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.template.fmt(f)
+    }
+}
+
+/// substitutes all placeholders of the template, fails if a value is not available
+pub(crate) fn render(
+    template: &Template,
+    profile: &DtzProfile,
+    values: &RequestValues,
+) -> Result<String, String> {
+    let mut result = String::new();
+    for part in template.parts() {
+        match part {
+            Part::Literal(literal) => result.push_str(literal),
+            Part::Placeholder(placeholder) => {
+                result.push_str(&resolve(placeholder, profile, values)?);
+            }
+        }
+    }
+    Ok(result)
+}
+
+/// checks whether the profile holds the rendered role
+pub(crate) fn is_granted(
+    template: &Template,
+    profile: &DtzProfile,
+    values: &RequestValues,
+) -> bool {
+    match render(template, profile, values) {
+        Ok(role) => crate::role::holds_role(&profile.roles, &role),
+        Err(_) => false,
+    }
+}
+
+fn resolve(
+    placeholder: &Placeholder,
+    profile: &DtzProfile,
+    values: &RequestValues,
+) -> Result<String, String> {
+    let value = match placeholder {
+        Placeholder::IdentityId => return Ok(profile.identity_id.to_string()),
+        Placeholder::ContextId => return Ok(profile.context_id.to_string()),
+        Placeholder::Roles => return Ok(profile.roles.join(",")),
+        Placeholder::Path(name) => values.path.get(name).cloned(),
+        Placeholder::Query(name) => values.query.get(name).cloned(),
+        Placeholder::Claim(name) => match profile.claims().get(name) {
+            Some(Value::String(value)) => Some(value.clone()),
+            Some(Value::Number(value)) => Some(value.to_string()),
+            Some(Value::Bool(value)) => Some(value.to_string()),
+            _ => None,
+        },
+    };
+    match value {
+        Some(value) => Ok(escape(&value)),
+        None => Err(format!("no value for placeholder {placeholder}")),
+    }
+}
+
+/// percent-encodes everything except unreserved characters
+fn escape(value: &str) -> String {
+    let mut result = String::new();
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                result.push(byte as char)
+            }
+            _ => result.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    result
+}
+
+/// path and query values of a request, used to render [`RoleTemplate`]s
+#[derive(Debug, Clone, Default)]
+pub struct RequestValues {
+    path: HashMap<String, String>,
+    query: HashMap<String, String>,
+}
+
+impl RequestValues {
+    /// creates empty values
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// adds a path parameter
+    pub fn with_path(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.path.insert(name.into(), value.into());
+        self
+    }
+
+    /// adds a query parameter
+    pub fn with_query(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.query.insert(name.into(), value.into());
+        self
+    }
+
+    /// value of a path parameter
+    pub fn path(&self, name: &str) -> Option<&str> {
+        self.path.get(name).map(String::as_str)
+    }
+
+    /// value of a query parameter
+    pub fn query(&self, name: &str) -> Option<&str> {
+        self.query.get(name).map(String::as_str)
+    }
+}
+
+impl<S> FromRequestParts<S> for RequestValues
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(req: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let mut values = RequestValues::new();
+        if let Ok(params) = RawPathParams::from_request_parts(req, state).await {
+            for (name, value) in &params {
+                values.path.insert(name.to_string(), value.to_string());
+            }
+        }
+        let query = req.uri.query().unwrap_or_default();
+        let pairs: Vec<(String, String)> = serde_urlencoded::from_str(query).unwrap_or_default();
+        values.query.extend(pairs);
+        Ok(values)
+    }
+}